@@ -0,0 +1,23 @@
+//! Timing helpers shared by the benchmarks. They use `harness = false`, so
+//! they run on stable Rust without any extra dependency.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Runs `f` repeatedly for about a second and prints the mean time per run.
+pub fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    // warm up
+    black_box(f());
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        runs += 1;
+    }
+    println!(
+        "{:<20} {:>12?} per run ({} runs)",
+        name,
+        start.elapsed() / runs,
+        runs
+    );
+}
@@ -0,0 +1,32 @@
+//! Compares `FrozenIndexSet::get_index` with
+//! `FrozenIndexSet::get_index_unchecked` in a bytecode-style dispatch loop,
+//! which looks up interned constants by the ids the interner handed out.
+//!
+//! Run with `cargo bench --features indexmap --bench get_index_unchecked`.
+
+mod common;
+
+use std::hint::black_box;
+
+use elsa::index_set::FrozenIndexSet;
+
+fn main() {
+    let constants: FrozenIndexSet<Box<u64>> = FrozenIndexSet::new();
+    let program: Vec<usize> = (0..4096u64)
+        .map(|i| constants.insert_full(Box::new(i * 7919 % 1024)).0)
+        .collect();
+    let program = black_box(&program);
+
+    common::bench("get_index", || {
+        program
+            .iter()
+            .map(|&id| *constants.get_index(id).unwrap())
+            .sum::<u64>()
+    });
+    common::bench("get_index_unchecked", || {
+        program
+            .iter()
+            .map(|&id| unsafe { *constants.get_index_unchecked(id) })
+            .sum::<u64>()
+    });
+}
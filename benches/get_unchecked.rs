@@ -0,0 +1,30 @@
+//! Compares `FrozenVec::get` with `FrozenVec::get_unchecked` in a loop over
+//! indices that are already known to be in bounds.
+//!
+//! Run with `cargo bench --bench get_unchecked`.
+
+mod common;
+
+use std::hint::black_box;
+
+use elsa::FrozenVec;
+
+fn main() {
+    // small enough to stay in cache, so that memory latency does not hide
+    // the cost of the bounds checks
+    let vec: FrozenVec<Box<u64>> = (0..4096).map(Box::new).collect();
+    // visit the elements out of order, through indices the optimizer cannot
+    // see, as a lookup table of validated ids would
+    let indices: Vec<usize> = (0..vec.len()).map(|i| i * 7919 % vec.len()).collect();
+    let indices = black_box(&indices);
+
+    common::bench("get", || {
+        indices.iter().map(|&i| *vec.get(i).unwrap()).sum::<u64>()
+    });
+    common::bench("get_unchecked", || {
+        indices
+            .iter()
+            .map(|&i| unsafe { *vec.get_unchecked(i) })
+            .sum::<u64>()
+    });
+}
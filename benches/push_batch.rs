@@ -0,0 +1,28 @@
+//! Compares `FrozenVec::push_batch` with pushing the same 10M-element stream
+//! one element at a time.
+//!
+//! Run with `cargo bench --bench push_batch`.
+
+mod common;
+
+use elsa::FrozenVec;
+
+const LEN: usize = 10_000_000;
+
+static ELEMENT: u64 = 0;
+
+fn main() {
+    // references do not allocate, so only the vector's own growth is timed
+    common::bench("push", || {
+        let vec: FrozenVec<&u64> = FrozenVec::new();
+        for _ in 0..LEN {
+            vec.push(&ELEMENT);
+        }
+        vec
+    });
+    common::bench("push_batch", || {
+        let vec: FrozenVec<&u64> = FrozenVec::new();
+        vec.push_batch((0..LEN).map(|_| &ELEMENT));
+        vec
+    });
+}
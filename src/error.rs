@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by the `try_` methods of the frozen collections when the
+/// collection is already in use, for example by an [`Eq`] or [`Hash`]
+/// implementation calling back into it.
+///
+/// It carries back the arguments that could not be inserted, so that the
+/// caller can retry later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReentrancyError<T> {
+    value: T,
+}
+
+impl<T> ReentrancyError<T> {
+    pub(crate) fn new(value: T) -> Self {
+        ReentrancyError { value }
+    }
+
+    /// Returns the arguments that could not be inserted.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Display for ReentrancyError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the collection is already in use")
+    }
+}
+
+impl<T: fmt::Debug> Error for ReentrancyError<T> {}
+
+/// Error returned by [`FrozenMap::get_or_insert_recursive`] when the value
+/// for a key is needed while it is still being computed.
+///
+/// It carries back the key, so that the caller can report the cycle.
+///
+/// [`FrozenMap::get_or_insert_recursive`]: crate::FrozenMap::get_or_insert_recursive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError<K> {
+    key: K,
+}
+
+impl<K> CycleError<K> {
+    pub(crate) fn new(key: K) -> Self {
+        CycleError { key }
+    }
+
+    /// Returns the key whose value depends on itself.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K> fmt::Display for CycleError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the value for the key is already being computed")
+    }
+}
+
+impl<K: fmt::Debug> Error for CycleError<K> {}
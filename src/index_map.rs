@@ -131,6 +131,38 @@ impl<K: Eq + Hash, V: StableDeref, S: BuildHasher> FrozenIndexMap<K, V, S> {
         ret
     }
 
+    /// Returns the index of the key and a reference to the corresponding value.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenIndexMap;
+    ///
+    /// let map = FrozenIndexMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// assert_eq!(map.get_full(&2), Some((1, &"b")));
+    /// assert_eq!(map.get_full(&3), None);
+    /// ```
+    pub fn get_full<Q: ?Sized>(&self, k: &Q) -> Option<(usize, &V::Target)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get_full(k).map(|(i, _k, v)| (i, &**v))
+        };
+        self.in_use.set(false);
+        ret
+    }
+
     /// Returns a reference to the key-value mapping corresponding to an index.
     ///
     /// The key may be any borrowed form of the map's key type, but
@@ -195,7 +227,57 @@ impl<K: Eq + Hash, V: StableDeref, S: BuildHasher> FrozenIndexMap<K, V, S> {
     }
 }
 
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenIndexMap<K, V, S> {
+    /// Returns the index of the key, if it is in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenIndexMap;
+    ///
+    /// let map = FrozenIndexMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// assert_eq!(map.get_index_of(&2), Some(1));
+    /// assert_eq!(map.get_index_of(&3), None);
+    /// ```
+    pub fn get_index_of<Q: ?Sized>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get_index_of(k)
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
 impl<K, V, S> FrozenIndexMap<K, V, S> {
+    /// Creates an empty map which will use the given hasher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenIndexMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let map = FrozenIndexMap::with_hasher(RandomState::new());
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::from(IndexMap::with_hasher(hasher))
+    }
+
     /// Collects the contents of this map into a vector of tuples.
     ///
     /// The order of the entries is as if iterating an [`IndexMap`].
@@ -228,6 +310,29 @@ impl<K, V, S> FrozenIndexMap<K, V, S> {
         unsafe { &mut *self.map.get() }
     }
 
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenIndexMap;
+    ///
+    /// let map = FrozenIndexMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).len()
+        };
+        self.in_use.set(false);
+        ret
+    }
+
     /// Returns true if the map contains no elements.
     pub fn is_empty(&self) -> bool {
         assert!(!self.in_use.get());
@@ -274,6 +379,44 @@ where
     }
 }
 
+impl<K, V: StableDeref, S> Index<usize> for FrozenIndexMap<K, V, S> {
+    type Output = V::Target;
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenIndexMap;
+    ///
+    /// let map = FrozenIndexMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map[0], "a");
+    ///
+    /// // indexing out of bounds panics, but leaves the map usable
+    /// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| map[1].len()));
+    /// assert!(result.is_err());
+    /// map.insert(2, Box::new("b"));
+    /// assert_eq!(map[1], "b");
+    /// ```
+    fn index(&self, idx: usize) -> &V::Target {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let (ret, len) = unsafe {
+            let map = &*self.map.get();
+            let ret = map
+                .get_index(idx)
+                .map(|(_, v)| &*(&**v as *const V::Target));
+            (ret, map.len())
+        };
+        self.in_use.set(false);
+        ret.unwrap_or_else(|| {
+            panic!(
+                "index out of bounds: the len is {} but the index is {}",
+                len, idx
+            )
+        })
+    }
+}
+
 impl<K: Eq + Hash, V, S: BuildHasher + Default> FromIterator<(K, V)> for FrozenIndexMap<K, V, S> {
     fn from_iter<T>(iter: T) -> Self
     where
@@ -306,7 +449,7 @@ impl<K: Clone, V: Clone, S: Clone> Clone for FrozenIndexMap<K, V, S> {
     }
 }
 
-impl<T: Hash + Eq, S: PartialEq> PartialEq for FrozenIndexMap<T, S> {
+impl<K: Hash + Eq, V: PartialEq, S: BuildHasher> PartialEq for FrozenIndexMap<K, V, S> {
     fn eq(&self, other: &Self) -> bool {
         assert!(!self.in_use.get());
         assert!(!other.in_use.get());
@@ -1,11 +1,16 @@
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use std::cell::{Cell, UnsafeCell};
+use std::cmp::Ordering;
 use std::collections::hash_map::RandomState;
 use std::hash::{BuildHasher, Hash};
 use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::ops::Index;
 
-use indexmap::IndexSet;
+use indexmap::set::MutableValues;
+use indexmap::{Equivalent, IndexMap, IndexSet, TryReserveError};
+
+use crate::FrozenIndexMap;
 use stable_deref_trait::StableDeref;
 
 #[cfg(feature = "serde")]
@@ -30,6 +35,29 @@ impl<T: Eq + Hash> FrozenIndexSet<T> {
     pub fn new() -> Self {
         Self::from(IndexSet::new())
     }
+
+    /// Returns a builder for configuring the capacity and hasher of a new set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let set: FrozenIndexSet<Box<str>> = FrozenIndexSet::builder()
+    ///     .capacity(16)
+    ///     .hasher(RandomState::new())
+    ///     .build();
+    /// set.insert("a".into());
+    /// assert_eq!(set.get_index(0), Some("a"));
+    /// ```
+    pub fn builder() -> FrozenIndexSetBuilder<T> {
+        FrozenIndexSetBuilder {
+            capacity: 0,
+            hasher: RandomState::new(),
+            set: PhantomData,
+        }
+    }
 }
 
 impl<T: Eq + Hash + StableDeref, S: BuildHasher> FrozenIndexSet<T, S> {
@@ -59,7 +87,7 @@ impl<T: Eq + Hash + StableDeref, S: BuildHasher> FrozenIndexSet<T, S> {
         let ret = unsafe {
             let set = self.set.get();
             let (index, _was_vacant) = (*set).insert_full(value);
-            &*(*set)[index]
+            &*(&*set)[index]
         };
         self.in_use.set(false);
         ret
@@ -86,7 +114,218 @@ impl<T: Eq + Hash + StableDeref, S: BuildHasher> FrozenIndexSet<T, S> {
         let ret = unsafe {
             let set = self.set.get();
             let (index, _was_vacant) = (*set).insert_full(value);
-            (index, &*(*set)[index])
+            (index, &*(&*set)[index])
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Inserts every value of `iter` into the set, returning, for each of them
+    /// in order, its index and whether it was newly inserted.
+    ///
+    /// Existing values are never overwritten, so duplicates get the index of
+    /// the value already in the set. The iterator is advanced without the set
+    /// being in use, so it may itself access the set.
+    ///
+    /// Room for the iterator's lower size bound is reserved up front with
+    /// [`FrozenIndexSet::try_reserve`]. If that allocation fails the values
+    /// are inserted as they come instead, so a huge size hint does not abort;
+    /// use [`FrozenIndexSet::try_extend_full`] to get the error instead.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// set.insert(Box::new("b"));
+    /// let ids = set.extend_full(vec![Box::new("a"), Box::new("b"), Box::new("a")]);
+    /// assert_eq!(ids, vec![(1, true), (0, false), (1, false)]);
+    /// ```
+    pub fn extend_full<I: IntoIterator<Item = T>>(&self, iter: I) -> Vec<(usize, bool)> {
+        let iter = iter.into_iter();
+        let additional = iter.size_hint().0;
+        // failing to reserve only means growing as the values are inserted
+        self.try_reserve(additional).ok();
+        let mut ret = Vec::new();
+        ret.try_reserve(additional).ok();
+        for value in iter {
+            assert!(!self.in_use.get());
+            self.in_use.set(true);
+            let full = unsafe {
+                let set = self.set.get();
+                (*set).insert_full(value)
+            };
+            self.in_use.set(false);
+            ret.push(full);
+        }
+        ret
+    }
+
+    /// Like [`FrozenIndexSet::extend_full`], but first tries to reserve room
+    /// for the iterator's lower size bound, returning an error without
+    /// inserting anything if that allocation fails.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// let ids = set.try_extend_full(vec![Box::new("a"), Box::new("b")]).unwrap();
+    /// assert_eq!(ids, vec![(0, true), (1, true)]);
+    ///
+    /// let huge = std::iter::repeat(Box::new("c")).take(usize::MAX);
+    /// assert!(set.try_extend_full(huge).is_err());
+    /// assert_eq!(set.get_index(2), None);
+    /// ```
+    pub fn try_extend_full<I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> Result<Vec<(usize, bool)>, TryReserveError> {
+        let iter = iter.into_iter();
+        self.try_reserve(iter.size_hint().0)?;
+        Ok(self.extend_full(iter))
+    }
+
+    /// Interns a value that may or may not already be owned, allocating as
+    /// little as possible.
+    ///
+    /// A borrowed value is only converted to its owned form if it is not already
+    /// in the set, and an owned value is inserted as-is.
+    ///
+    /// Existing values are never overwritten.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::borrow::Cow;
+    ///
+    /// let set: FrozenIndexSet<String> = FrozenIndexSet::new();
+    /// let a = set.get_or_insert_cow(Cow::<str>::Owned(String::from("a")));
+    /// assert!(std::ptr::eq(a, set.get_or_insert_cow(Cow::Borrowed("a"))));
+    /// assert_eq!(set.get_or_insert_cow(Cow::Borrowed("b")), "b");
+    /// ```
+    pub fn get_or_insert_cow<Q: ?Sized>(&self, value: Cow<'_, Q>) -> &T::Target
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = T>,
+    {
+        match value {
+            Cow::Owned(value) => self.insert(value),
+            Cow::Borrowed(value) => match self.get(value) {
+                Some(existing) => existing,
+                None => self.insert(value.to_owned()),
+            },
+        }
+    }
+
+    /// If an equal value exists in the set, returns a reference to it, otherwise
+    /// inserts a clone of `value` and returns a reference to that.
+    ///
+    /// `value` is only cloned if it is missing, so interning an already present
+    /// value costs nothing beyond the lookup.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::sync::Arc;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// let blob = Arc::new(String::from("a"));
+    /// assert_eq!(set.get_or_insert_ref(&blob), "a");
+    /// assert_eq!(Arc::strong_count(&blob), 2);
+    /// set.get_or_insert_ref(&blob);
+    /// assert_eq!(Arc::strong_count(&blob), 2);
+    /// ```
+    pub fn get_or_insert_ref(&self, value: &T) -> &T::Target
+    where
+        T: Clone,
+    {
+        match self.get(value) {
+            Some(existing) => existing,
+            None => self.insert(value.clone()),
+        }
+    }
+
+    /// Like [`FrozenIndexSet::insert`], but if an equal value is already in the
+    /// set, `value` is handed back instead of being dropped.
+    ///
+    /// Returns a reference to the value in the set, and `Some(value)` if it
+    /// was a duplicate or `None` if it was inserted.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// assert_eq!(set.get_or_insert_returning_dup(Box::new("a")), (&"a", None));
+    /// let (a, dup) = set.get_or_insert_returning_dup(Box::new("a"));
+    /// assert_eq!(a, &"a");
+    /// assert_eq!(dup, Some(Box::new("a")));
+    /// ```
+    pub fn get_or_insert_returning_dup(&self, value: T) -> (&T::Target, Option<T>) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = &mut *self.set.get();
+            match set.get_index_of(&value) {
+                Some(index) => (&*(&*set[index] as *const T::Target), Some(value)),
+                None => {
+                    let (index, _was_vacant) = set.insert_full(value);
+                    (&*(&*set[index] as *const T::Target), None)
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Looks up a value by any key [`Equivalent`] to it, calling `make` to
+    /// create and insert the value if it is missing.
+    ///
+    /// Returns the index of the value, a reference to it, and `true` if it was
+    /// newly inserted. Existing values are never overwritten.
+    ///
+    /// `make` is called without the set being in use, so it may itself access
+    /// the set. If it inserts an equal value, that value is kept and returned.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set: FrozenIndexSet<String> = FrozenIndexSet::new();
+    /// assert_eq!(set.get_or_insert_equivalent_full("a", || "a".to_string()), (0, "a", true));
+    /// assert_eq!(set.get_or_insert_equivalent_full("a", || unreachable!()), (0, "a", false));
+    /// assert_eq!(set.get_or_insert_equivalent_full("b", || "b".to_string()), (1, "b", true));
+    /// ```
+    pub fn get_or_insert_equivalent_full<Q: ?Sized, F>(
+        &self,
+        key: &Q,
+        make: F,
+    ) -> (usize, &T::Target, bool)
+    where
+        Q: Hash + Equivalent<T>,
+        F: FnOnce() -> T,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let existing = unsafe {
+            let set = self.set.get();
+            (*set)
+                .get_full(key)
+                .map(|(index, value)| (index, &*(&**value as *const T::Target)))
+        };
+        self.in_use.set(false);
+        if let Some((index, value)) = existing {
+            return (index, value, false);
+        }
+
+        let value = make();
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = &mut *self.set.get();
+            let (index, was_vacant) = set.insert_full(value);
+            (index, &*(&*set[index] as *const T::Target), was_vacant)
         };
         self.in_use.set(false);
         ret
@@ -197,13 +436,187 @@ impl<T: Eq + Hash + StableDeref, S: BuildHasher> FrozenIndexSet<T, S> {
         self.in_use.set(false);
         ret
     }
+
+    /// Returns a reference to the value at the index passed as argument,
+    /// without doing bounds checking.
+    ///
+    /// ## Safety
+    ///
+    /// `index` must be in bounds, i.e. it must be less than the number of
+    /// values in the set. This is checked with a debug assertion in debug builds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// let (index, _) = set.insert_full(Box::new("a"));
+    /// assert_eq!(unsafe { set.get_index_unchecked(index) }, &"a");
+    /// ```
+    pub unsafe fn get_index_unchecked(&self, index: usize) -> &T::Target {
+        // no user code runs here, so unlike `get_index` the set is not marked
+        // in use, but it must not be in the middle of an insertion
+        assert!(!self.in_use.get());
+        let set = &*self.set.get();
+        debug_assert!(index < set.len());
+        set.get_index(index).unwrap_unchecked()
+    }
+
+    /// Returns an iterator over the values of the set, in insertion order.
+    ///
+    /// The iterator only uses the set while advancing, so it can be cloned to
+    /// look ahead, and values may be inserted while it is alive; those are
+    /// yielded too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// set.insert(Box::new("a"));
+    /// set.insert(Box::new("b"));
+    /// let mut iter = set.iter();
+    /// assert_eq!(iter.next(), Some(&"a"));
+    /// let mut lookahead = iter.clone();
+    /// assert_eq!(lookahead.next(), Some(&"b"));
+    /// assert_eq!(lookahead.next(), None);
+    /// set.insert(Box::new("c"));
+    /// assert_eq!(iter.collect::<Vec<_>>(), vec![&"b", &"c"]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter { set: self, idx: 0 }
+    }
+
+    /// Reserves capacity for at least `additional` more values.
+    ///
+    /// This may reallocate, but since the values are [`StableDeref`], and no
+    /// values are removed, previously returned references stay valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set: FrozenIndexSet<Box<i32>> = FrozenIndexSet::new();
+    /// set.reserve(100);
+    /// let a = set.insert(Box::new(1));
+    /// set.reserve(1000);
+    /// assert!(std::ptr::eq(a, &set[0]));
+    /// ```
+    pub fn reserve(&self, additional: usize) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            let set = self.set.get();
+            (*set).reserve(additional)
+        };
+        self.in_use.set(false);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more values,
+    /// returning an error instead of aborting if the allocation fails.
+    ///
+    /// On success, returns the number of values in the set before reserving,
+    /// which is the index the next newly inserted value will get.
+    ///
+    /// This may reallocate, but since the values are [`StableDeref`], and no
+    /// values are removed, previously returned references stay valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set: FrozenIndexSet<Box<i32>> = FrozenIndexSet::new();
+    /// assert_eq!(set.try_reserve(100), Ok(0));
+    /// set.insert(Box::new(1));
+    /// assert_eq!(set.try_reserve(100), Ok(1));
+    /// assert!(set.try_reserve(usize::MAX).is_err());
+    /// ```
+    pub fn try_reserve(&self, additional: usize) -> Result<usize, TryReserveError> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = &mut *self.set.get();
+            set.try_reserve(additional).map(|()| set.len())
+        };
+        self.in_use.set(false);
+        ret
+    }
 }
 
 impl<T, S> FrozenIndexSet<T, S> {
+    /// Creates an empty set which will use the given hasher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let set = FrozenIndexSet::with_hasher(RandomState::new());
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.get_index(0), Some(&"a"));
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::from(IndexSet::with_hasher(hasher))
+    }
+
+    /// Creates an empty set with room for at least `capacity` values, which
+    /// will use the given hasher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let set = FrozenIndexSet::with_capacity_and_hasher(16, RandomState::new());
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.get_index(0), Some(&"a"));
+    /// ```
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self::from(IndexSet::with_capacity_and_hasher(capacity, hasher))
+    }
+
+    /// Returns a reference to the set's hasher.
+    ///
+    /// This requires a `&mut self`, as the hasher is stored inline in the set,
+    /// which insertions borrow mutably.
+    pub fn hasher(&mut self) -> &S {
+        self.set.get_mut().hasher()
+    }
+
     pub fn into_set(self) -> IndexSet<T, S> {
         self.set.into_inner()
     }
 
+    /// Freezes a standard [`IndexSet`]. This is the same as the [`From`] conversion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use indexmap::IndexSet;
+    ///
+    /// let mut std = IndexSet::new();
+    /// std.insert(Box::new("a"));
+    /// let frozen = FrozenIndexSet::from_std(std);
+    /// assert_eq!(frozen.get_index(0), Some(&"a"));
+    /// let std: IndexSet<_, _> = frozen.into_std();
+    /// assert_eq!(std.len(), 1);
+    /// ```
+    pub fn from_std(set: IndexSet<T, S>) -> Self {
+        Self::from(set)
+    }
+
+    /// Unfreezes into the standard [`IndexSet`].
+    pub fn into_std(self) -> IndexSet<T, S> {
+        self.into_set()
+    }
+
     /// Get mutable access to the underlying [`IndexSet`].
     ///
     /// This is safe, as it requires a `&mut self`, ensuring nothing is using
@@ -212,9 +625,372 @@ impl<T, S> FrozenIndexSet<T, S> {
         unsafe { &mut *self.set.get() }
     }
 
+    /// Shortens the set, keeping the first `len` elements and dropping the rest.
+    ///
+    /// Indices below `len` are unaffected. This has no effect if `len` is
+    /// greater than the set's current length.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set = FrozenIndexSet::new();
+    /// set.insert(Box::new("a"));
+    /// set.insert(Box::new("b"));
+    /// set.truncate(1);
+    /// assert_eq!(set.get_index(0), Some(&"a"));
+    /// assert_eq!(set.get_index(1), None);
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        self.set.get_mut().truncate(len)
+    }
+
+    /// Sorts the set's values with a comparator function, analogous to
+    /// [slice::sort_unstable_by], returning the mapping that was applied: the
+    /// value previously at index `i` is now at index `mapping[i]`.
+    ///
+    /// **Note** that this invalidates any indices kept around, such as ids
+    /// previously returned by [`FrozenIndexSet::insert_full`]; the returned
+    /// mapping can be used to rewrite them.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set = FrozenIndexSet::new();
+    /// set.insert(Box::new("c"));
+    /// set.insert(Box::new("a"));
+    /// set.insert(Box::new("b"));
+    /// assert_eq!(set.sort_unstable_by(|a, b| a.cmp(b)), vec![2, 0, 1]);
+    /// assert_eq!(set.get_index(0), Some(&"a"));
+    /// assert_eq!(set.get_index(2), Some(&"c"));
+    /// ```
+    pub fn sort_unstable_by<F>(&mut self, mut f: F) -> Vec<usize>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let set = self.set.get_mut();
+        let mut order: Vec<usize> = (0..set.len()).collect();
+        order.sort_unstable_by(|&a, &b| f(&set[a], &set[b]));
+
+        let mut mapping = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            mapping[old] = new;
+        }
+
+        // each swap moves the value at `i` to its final position
+        let mut targets = mapping.clone();
+        for i in 0..targets.len() {
+            while targets[i] != i {
+                let j = targets[i];
+                set.swap_indices(i, j);
+                targets.swap(i, j);
+            }
+        }
+        mapping
+    }
+
     // TODO add more
 }
 
+impl<T: Eq + Hash, S: BuildHasher> FrozenIndexSet<T, S> {
+    /// Adds a value to the set, replacing the existing value equal to it, if any.
+    /// Returns the replaced value.
+    ///
+    /// The index of the value is preserved, but the stored object is the new
+    /// one, so its address (and anything else [`Eq`] ignores) may change.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set = FrozenIndexSet::new();
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.replace(Box::new("a")), Some(Box::new("a")));
+    /// assert_eq!(set.replace(Box::new("b")), None);
+    /// assert_eq!(set.get_full(&Box::new("a")), Some((0, &"a")));
+    /// ```
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        self.set.get_mut().replace(value)
+    }
+
+    /// Adds a value to the set, or if a value equal to it is already present,
+    /// calls `merge` to fold the new value into the stored one. Returns the
+    /// index of the value and whether it was inserted.
+    ///
+    /// `merge` must not change how the stored value hashes or compares,
+    /// otherwise it can no longer be found in the set. The stored value is
+    /// updated in place, so it keeps its index even if `merge` panics.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    /// use std::hash::{Hash, Hasher};
+    ///
+    /// struct Symbol {
+    ///     name: &'static str,
+    ///     spans: Vec<u32>,
+    /// }
+    ///
+    /// impl PartialEq for Symbol {
+    ///     fn eq(&self, other: &Self) -> bool {
+    ///         self.name == other.name
+    ///     }
+    /// }
+    ///
+    /// impl Eq for Symbol {}
+    ///
+    /// impl Hash for Symbol {
+    ///     fn hash<H: Hasher>(&self, state: &mut H) {
+    ///         self.name.hash(state)
+    ///     }
+    /// }
+    ///
+    /// let mut set = FrozenIndexSet::new();
+    /// let merge = |stored: &mut Box<Symbol>, new: Box<Symbol>| stored.spans.extend(new.spans);
+    /// let a = |span| Box::new(Symbol { name: "a", spans: vec![span] });
+    /// set.insert(Box::new(Symbol { name: "b", spans: vec![] }));
+    /// assert_eq!(set.get_or_insert_merging(a(1), merge), (1, true));
+    /// set.insert(Box::new(Symbol { name: "c", spans: vec![] }));
+    /// assert_eq!(set.get_or_insert_merging(a(2), merge), (1, false));
+    /// assert_eq!(set.get_index(1).unwrap().spans, vec![1, 2]);
+    /// assert_eq!(set.get_index(2).unwrap().name, "c");
+    /// ```
+    pub fn get_or_insert_merging<F: FnOnce(&mut T, T)>(
+        &mut self,
+        value: T,
+        merge: F,
+    ) -> (usize, bool) {
+        let set = self.set.get_mut();
+        match set.get_full_mut2(&value) {
+            Some((index, existing)) => {
+                merge(existing, value);
+                (index, false)
+            }
+            None => set.insert_full(value),
+        }
+    }
+
+    /// Converts the set into a [`FrozenIndexMap`] with the values of the set
+    /// as keys, calling `f` with the index and value of each to produce its
+    /// map value. Every key keeps the index it had in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let set = FrozenIndexSet::new();
+    /// set.insert(Box::new("a"));
+    /// set.insert(Box::new("b"));
+    /// let map = set.into_index_map(|i, name| Box::new(format!("{}{}", name, i)));
+    /// assert_eq!(map.get_index(1), Some((&"b", &"b1".to_string())));
+    /// ```
+    pub fn into_index_map<V, F>(self, mut f: F) -> FrozenIndexMap<T, V, S>
+    where
+        S: Clone,
+        F: FnMut(usize, &T) -> V,
+    {
+        let set = self.set.into_inner();
+        let mut map = IndexMap::with_capacity_and_hasher(set.len(), set.hasher().clone());
+        for (i, value) in set.into_iter().enumerate() {
+            let v = f(i, &value);
+            map.insert(value, v);
+        }
+        map.into()
+    }
+
+    /// Removes a value from the set, returning whether it was present.
+    ///
+    /// The order of the remaining values is preserved: **note** that every
+    /// value after the removed one moves down by one index. This is O(n).
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set: FrozenIndexSet<_> = vec![Box::new("a"), Box::new("b"), Box::new("c")]
+    ///     .into_iter()
+    ///     .collect();
+    /// assert!(set.shift_remove(&Box::new("a")));
+    /// assert!(!set.shift_remove(&Box::new("a")));
+    /// assert_eq!(set.get_full(&Box::new("b")), Some((0, &"b")));
+    /// assert_eq!(set.get_full(&Box::new("c")), Some((1, &"c")));
+    /// ```
+    pub fn shift_remove<Q: ?Sized>(&mut self, k: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.set.get_mut().shift_remove(k)
+    }
+
+    /// Removes a value from the set, returning whether it was present.
+    ///
+    /// The last value takes the place of the removed one: **note** that this
+    /// changes its index, and so the order of the set. This is O(1).
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set: FrozenIndexSet<_> = vec![Box::new("a"), Box::new("b"), Box::new("c")]
+    ///     .into_iter()
+    ///     .collect();
+    /// assert!(set.swap_remove(&Box::new("a")));
+    /// assert_eq!(set.get_full(&Box::new("c")), Some((0, &"c")));
+    /// assert_eq!(set.get_full(&Box::new("b")), Some((1, &"b")));
+    /// ```
+    pub fn swap_remove<Q: ?Sized>(&mut self, k: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.set.get_mut().swap_remove(k)
+    }
+
+    /// Removes every value whose key, as computed by `f`, equals the key of an
+    /// earlier value, keeping the first of each group.
+    ///
+    /// Returns the remapping of indices: the value that was at index `i` is
+    /// now at `remap[i]`, or `remap[i]` is `None` if it was removed. **Note**
+    /// that this shifts the indices of values after a removed one, which can
+    /// be fixed up using the remapping.
+    ///
+    /// Keys are only compared for equality, so each key is compared with the
+    /// keys of all the values kept before it.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let mut set: FrozenIndexSet<_> = vec![Box::new("a"), Box::new("B"), Box::new("A"), Box::new("c")]
+    ///     .into_iter()
+    ///     .collect();
+    /// let remap = set.dedup_by_key(|s| s.to_lowercase());
+    /// assert_eq!(remap, vec![Some(0), Some(1), None, Some(2)]);
+    /// assert_eq!(set.get_index(2), Some(&"c"));
+    /// assert_eq!(set.get_index(3), None);
+    /// ```
+    pub fn dedup_by_key<K2: Eq, F: FnMut(&T) -> K2>(&mut self, mut f: F) -> Vec<Option<usize>> {
+        let set = self.set.get_mut();
+        let mut kept_keys: Vec<K2> = Vec::new();
+        let remap: Vec<Option<usize>> = set
+            .iter()
+            .map(|value| {
+                let key = f(value);
+                if kept_keys.contains(&key) {
+                    None
+                } else {
+                    kept_keys.push(key);
+                    Some(kept_keys.len() - 1)
+                }
+            })
+            .collect();
+        let mut kept = remap.iter();
+        set.retain(|_| kept.next().unwrap().is_some());
+        remap
+    }
+
+    /// Returns `true` if `self` has no values in common with `other`.
+    ///
+    /// Both sets must not be in use, so a set cannot be compared with itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let a: FrozenIndexSet<_> = vec![Box::new(1), Box::new(2)].into_iter().collect();
+    /// let b: FrozenIndexSet<_> = vec![Box::new(3)].into_iter().collect();
+    /// assert!(a.is_disjoint(&b));
+    /// b.insert(Box::new(2));
+    /// assert!(!a.is_disjoint(&b));
+    /// ```
+    pub fn is_disjoint<S2: BuildHasher>(&self, other: &FrozenIndexSet<T, S2>) -> bool {
+        self.with_both(other, |set, other| set.is_disjoint(other))
+    }
+
+    /// Returns `true` if all values of `self` are contained in `other`.
+    ///
+    /// Both sets must not be in use, so a set cannot be compared with itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let a: FrozenIndexSet<_> = vec![Box::new(1)].into_iter().collect();
+    /// let b: FrozenIndexSet<_> = vec![Box::new(1), Box::new(2)].into_iter().collect();
+    /// assert!(a.is_subset(&b));
+    /// assert!(!b.is_subset(&a));
+    /// ```
+    pub fn is_subset<S2: BuildHasher>(&self, other: &FrozenIndexSet<T, S2>) -> bool {
+        self.with_both(other, |set, other| set.is_subset(other))
+    }
+
+    /// Returns `true` if all values of `other` are contained in `self`.
+    ///
+    /// Both sets must not be in use, so a set cannot be compared with itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::index_set::FrozenIndexSet;
+    ///
+    /// let a: FrozenIndexSet<_> = vec![Box::new(1), Box::new(2)].into_iter().collect();
+    /// let b: FrozenIndexSet<_> = vec![Box::new(2)].into_iter().collect();
+    /// assert!(a.is_superset(&b));
+    /// assert!(!b.is_superset(&a));
+    /// ```
+    pub fn is_superset<S2: BuildHasher>(&self, other: &FrozenIndexSet<T, S2>) -> bool {
+        self.with_both(other, |set, other| set.is_superset(other))
+    }
+
+    fn with_both<S2, R>(
+        &self,
+        other: &FrozenIndexSet<T, S2>,
+        f: impl FnOnce(&IndexSet<T, S>, &IndexSet<T, S2>) -> R,
+    ) -> R {
+        assert!(!self.in_use.get());
+        assert!(!other.in_use.get());
+        self.in_use.set(true);
+        other.in_use.set(true);
+        let ret = unsafe { f(&*self.set.get(), &*other.set.get()) };
+        self.in_use.set(false);
+        other.in_use.set(false);
+        ret
+    }
+}
+
 impl<T, S> From<IndexSet<T, S>> for FrozenIndexSet<T, S> {
     fn from(set: IndexSet<T, S>) -> Self {
         Self {
@@ -224,6 +1000,36 @@ impl<T, S> From<IndexSet<T, S>> for FrozenIndexSet<T, S> {
     }
 }
 
+/// Builder for a [`FrozenIndexSet`], obtained via [`FrozenIndexSet::builder`].
+#[derive(Debug, Clone)]
+pub struct FrozenIndexSetBuilder<T, S = RandomState> {
+    capacity: usize,
+    hasher: S,
+    set: PhantomData<fn() -> T>,
+}
+
+impl<T, S> FrozenIndexSetBuilder<T, S> {
+    /// Sets the number of values the set has room for before reallocating.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the hasher the set will use.
+    pub fn hasher<S2>(self, hasher: S2) -> FrozenIndexSetBuilder<T, S2> {
+        FrozenIndexSetBuilder {
+            capacity: self.capacity,
+            hasher,
+            set: PhantomData,
+        }
+    }
+
+    /// Creates the configured set.
+    pub fn build(self) -> FrozenIndexSet<T, S> {
+        FrozenIndexSet::with_capacity_and_hasher(self.capacity, self.hasher)
+    }
+}
+
 impl<T: Eq + Hash + StableDeref, S> Index<usize> for FrozenIndexSet<T, S> {
     type Output = T::Target;
     fn index(&self, idx: usize) -> &T::Target {
@@ -231,13 +1037,47 @@ impl<T: Eq + Hash + StableDeref, S> Index<usize> for FrozenIndexSet<T, S> {
         self.in_use.set(true);
         let ret = unsafe {
             let set = self.set.get();
-            &*(*set)[idx]
+            &*(&*set)[idx]
         };
         self.in_use.set(false);
         ret
     }
 }
 
+/// Iterator over FrozenIndexSet, obtained via `.iter()`
+///
+/// It is safe to insert into the set during iteration
+pub struct Iter<'a, T, S> {
+    set: &'a FrozenIndexSet<T, S>,
+    idx: usize,
+}
+
+impl<'a, T, S> Clone for Iter<'a, T, S> {
+    fn clone(&self) -> Self {
+        Iter {
+            set: self.set,
+            idx: self.idx,
+        }
+    }
+}
+
+impl<'a, T: Eq + Hash + StableDeref, S: BuildHasher> Iterator for Iter<'a, T, S> {
+    type Item = &'a T::Target;
+    fn next(&mut self) -> Option<&'a T::Target> {
+        let ret = self.set.get_index(self.idx)?;
+        self.idx += 1;
+        Some(ret)
+    }
+}
+
+impl<'a, T: Eq + Hash + StableDeref, S: BuildHasher> IntoIterator for &'a FrozenIndexSet<T, S> {
+    type Item = &'a T::Target;
+    type IntoIter = Iter<'a, T, S>;
+    fn into_iter(self) -> Iter<'a, T, S> {
+        self.iter()
+    }
+}
+
 impl<T: Eq + Hash, S: Default + BuildHasher> FromIterator<T> for FrozenIndexSet<T, S> {
     fn from_iter<U>(iter: U) -> Self
     where
@@ -254,7 +1094,7 @@ impl<T: Eq + Hash, S: Default> Default for FrozenIndexSet<T, S> {
     }
 }
 
-impl<K: Clone, V: Clone> Clone for FrozenIndexSet<K, V> {
+impl<T: Clone, S: Clone> Clone for FrozenIndexSet<T, S> {
     fn clone(&self) -> Self {
         assert!(!self.in_use.get());
         self.in_use.set(true);
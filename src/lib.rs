@@ -10,7 +10,10 @@
 //!
 //! The typical use case is having a global cache of strings or other data which the rest of the program borrows from.
 
+pub mod error;
+mod macros;
 pub mod map;
+pub mod set;
 pub mod vec;
 
 #[cfg(feature = "indexmap")]
@@ -20,7 +23,9 @@ pub mod index_set;
 
 pub mod sync;
 
-pub use map::{FrozenBTreeMap, FrozenMap};
+pub use error::{CycleError, ReentrancyError};
+pub use map::{FrozenBTreeMap, FrozenLruMap, FrozenMap, FrozenTtlMap};
+pub use set::FrozenSet;
 pub use vec::FrozenVec;
 
 #[cfg(feature = "indexmap")]
@@ -0,0 +1,53 @@
+/// Creates a [`FrozenMap`](crate::FrozenMap) containing the given entries.
+///
+/// Entries are inserted in order, so as with [`FrozenMap::insert`](crate::FrozenMap::insert)
+/// and [`FrozenMap::from_iter_sized`](crate::FrozenMap::from_iter_sized) a
+/// repeated key keeps its first value.
+///
+/// # Examples
+///
+/// ```
+/// use elsa::frozen_map;
+///
+/// let map = frozen_map! {
+///     "a" => Box::new(1),
+///     "b" => Box::new(2),
+///     "a" => Box::new(3),
+/// };
+/// assert_eq!(map.get("a"), Some(&1));
+/// assert_eq!(map.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! frozen_map {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        let map = $crate::FrozenMap::new();
+        $(
+            map.insert($key, $value);
+        )*
+        map
+    }};
+}
+
+/// Creates a [`FrozenVec`](crate::FrozenVec) containing the given elements,
+/// with the same syntax as [`vec!`].
+///
+/// # Examples
+///
+/// ```
+/// use elsa::frozen_vec;
+///
+/// let vec = frozen_vec![String::from("a"), String::from("b")];
+/// assert_eq!(vec.get(1), Some("b"));
+///
+/// let vec = frozen_vec![Box::new(0); 3];
+/// assert_eq!(vec.len(), 3);
+/// ```
+#[macro_export]
+macro_rules! frozen_vec {
+    ($elem:expr; $n:expr) => {
+        $crate::FrozenVec::from(::std::vec![$elem; $n])
+    };
+    ($($elem:expr),* $(,)?) => {
+        $crate::FrozenVec::from(::std::vec![$($elem),*])
+    };
+}
@@ -1,14 +1,22 @@
+use std::any::Any;
 use std::borrow::Borrow;
 use std::cell::{Cell, UnsafeCell};
-use std::collections::hash_map::RandomState;
+use std::collections::btree_map::RangeMut;
+use std::collections::hash_map::{self, DefaultHasher, Drain, RandomState, ValuesMut};
 use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::hash::{BuildHasher, Hash};
+use std::collections::{HashMap, HashSet, TryReserveError};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::iter::FromIterator;
-use std::ops::Index;
+use std::ops::{Index, RangeBounds};
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use stable_deref_trait::StableDeref;
 
+use crate::{CycleError, ReentrancyError};
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -23,51 +31,291 @@ pub struct FrozenMap<K, V, S = RandomState> {
     ///
     /// We use this `in_use` flag to guard against any reentrancy.
     in_use: Cell<bool>,
+    /// Only allocated for maps created with `with_stats`
+    stats: Option<Box<StatsCounters>>,
+    /// Keys reserved by a live [`Slot`], which have no value yet. Only
+    /// allocated by the first `reserve_slot`
+    pending: UnsafeCell<Option<Box<Pending<K>>>>,
+    /// Only allocated by `on_miss`
+    miss_hook: UnsafeCell<Option<Box<MissHook<K>>>>,
+    /// Only allocated for maps created with `with_indexing`
+    order: Option<Box<UnsafeCell<Order<K>>>>,
 }
 
 // safety: UnsafeCell implies !Sync
 
 impl<K: Eq + Hash, V> FrozenMap<K, V> {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map which counts its lookups, see [`FrozenMap::stats`].
+    ///
+    /// Maps created otherwise do not count anything, and only pay for checking
+    /// that they do not.
+    pub fn with_stats() -> Self {
         Self {
-            map: UnsafeCell::new(Default::default()),
-            in_use: Cell::new(false),
+            stats: Some(Box::default()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty map which also records the order in which keys are
+    /// inserted, giving each a stable index, see [`FrozenMap::get_index`] and
+    /// [`FrozenMap::insert_full`].
+    ///
+    /// Lookups by key cost the same as in other maps. Inserting a new key
+    /// additionally stores a clone of it, while finding the index of a key
+    /// that is already present and removing a key take time linear in the
+    /// length of the map.
+    ///
+    /// Keys inserted through [`AsMut`] get indices after all other keys, in
+    /// arbitrary order.
+    pub fn with_indexing() -> Self
+    where
+        K: Clone,
+    {
+        Self {
+            order: Some(Box::new(UnsafeCell::new(Order {
+                keys: Vec::new(),
+                stale: false,
+                clone_key: K::clone,
+            }))),
+            ..Self::default()
         }
     }
+}
 
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenMap<K, V, S> {
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
     /// # Examples
     ///
     /// ```
     /// use elsa::FrozenMap;
     ///
     /// let map = FrozenMap::new();
-    /// assert_eq!(map.len(), 0);
     /// map.insert(1, Box::new("a"));
-    /// assert_eq!(map.len(), 1);
+    /// assert!(map.contains_key(&1));
+    /// assert!(!map.contains_key(&2));
     /// ```
-    pub fn len(&self) -> usize {
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
         assert!(!self.in_use.get());
         self.in_use.set(true);
-        let len = unsafe {
+        let ret = unsafe {
             let map = self.map.get();
-            (*map).len()
+            (*map).contains_key(k)
         };
         self.in_use.set(false);
-        len
+        ret
     }
 
+    /// Creates a map from the entries of an iterator, reserving room for the
+    /// iterator's lower size bound up front.
+    ///
+    /// As with [`FrozenMap::insert`] and [`frozen_map!`](crate::frozen_map), a
+    /// repeated key keeps its first value. Collecting into a [`FrozenMap`]
+    /// reserves in the same way, but keeps the last value, as `HashMap` does.
+    ///
     /// # Examples
     ///
     /// ```
     /// use elsa::FrozenMap;
     ///
-    /// let map = FrozenMap::new();
-    /// assert_eq!(map.is_empty(), true);
+    /// let map: FrozenMap<_, _> = FrozenMap::from_iter_sized((0..100).map(|i| (i, Box::new(i))));
+    /// assert_eq!(map.len(), 100);
+    /// assert!(map.capacity() >= 100);
+    ///
+    /// let map: FrozenMap<_, _> = FrozenMap::from_iter_sized([(1, Box::new("a")), (1, Box::new("b"))]);
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// ```
+    pub fn from_iter_sized<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self
+    where
+        S: Default,
+    {
+        let iter = iter.into_iter();
+        let mut map = HashMap::with_capacity_and_hasher(iter.size_hint().0, S::default());
+        for (k, v) in iter {
+            map.entry(k).or_insert(v);
+        }
+        map.into()
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
     /// map.insert(1, Box::new("a"));
-    /// assert_eq!(map.is_empty(), false);
+    /// assert_eq!(map.remove(&1), Some(Box::new("a")));
+    /// assert_eq!(map.remove(&1), None);
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        if let Some(order) = &mut self.order {
+            order.get_mut().remove(k);
+        }
+        self.map.get_mut().remove(k)
+    }
+
+    /// Inserts a value for the key, overwriting and returning the previous
+    /// value if there was one. A new key is recorded with its index in maps
+    /// created with [`FrozenMap::with_indexing`]; an existing key keeps its index.
+    ///
+    /// Unlike [`FrozenMap::insert`], this drops the previous value, so it
+    /// requires a `&mut self`, ensuring nothing is using the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
+    /// assert_eq!(map.replace(1, Box::new("a")), None);
+    /// assert_eq!(map.replace(1, Box::new("b")), Some(Box::new("a")));
+    /// assert_eq!(map.get(&1), Some(&"b"));
+    /// ```
+    pub fn replace(&mut self, k: K, v: V) -> Option<V> {
+        match self.map.get_mut().entry(k) {
+            hash_map::Entry::Occupied(mut entry) => Some(entry.insert(v)),
+            hash_map::Entry::Vacant(entry) => {
+                if let Some(order) = &mut self.order {
+                    order.get_mut().push(entry.key());
+                }
+                entry.insert(v);
+                None
+            }
+        }
+    }
+
+    /// If the key exists in the map, calls `f` with it and a mutable reference
+    /// to its value, returning `true`; otherwise returns `false`. This is the
+    /// counterpart to [`FrozenMap::get_or_insert_default`] for updating values.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// The entry is updated in place, so if `f` panics it stays in the map
+    /// with whatever `f` left in the value.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
+    /// map.insert("a", Box::new(1));
+    /// assert!(map.compute_if_present("a", |k, v| **v += k.len()));
+    /// assert!(!map.compute_if_present("b", |_, _| unreachable!()));
+    /// assert_eq!(map.get("a"), Some(&2));
+    /// ```
+    pub fn compute_if_present<Q: ?Sized, F>(&mut self, k: &Q, f: F) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+        F: FnOnce(&K, &mut V),
+    {
+        let map = self.map.get_mut();
+        // `HashMap` has no way to borrow the key along with a mutable value,
+        // so the value is looked up first and kept as a pointer while the key
+        // is looked up: that only reads the entry, nothing moves or is written.
+        let v: *mut V = match map.get_mut(k) {
+            Some(v) => v,
+            None => return false,
+        };
+        let (key, _) = map.get_key_value(k).unwrap();
+        f(key, unsafe { &mut *v });
+        true
+    }
+
+    /// Returns mutable references to the values of several keys at once, or
+    /// `None` if any of the keys is missing or two of them are equal.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
+    /// map.insert("a", Box::new(1));
+    /// map.insert("b", Box::new(2));
+    /// if let Some([a, b]) = map.get_many_mut(["a", "b"]) {
+    ///     std::mem::swap(a, b);
+    /// }
+    /// assert_eq!(map.get("a"), Some(&2));
+    /// assert!(map.get_many_mut(["a", "a"]).is_none());
+    /// assert!(map.get_many_mut(["a", "c"]).is_none());
+    /// ```
+    pub fn get_many_mut<Q: ?Sized, const N: usize>(&mut self, ks: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let map = self.map.get_mut();
+        let mut values = [std::ptr::null_mut::<V>(); N];
+        for (i, k) in ks.iter().enumerate() {
+            let value: *mut V = map.get_mut(*k)?;
+            if values[..i].contains(&value) {
+                return None;
+            }
+            values[i] = value;
+        }
+        // safety: the pointers are all distinct, and point into the map, which
+        // is borrowed mutably for as long as the references live
+        Some(values.map(|value| unsafe { &mut *value }))
+    }
+
+    /// Retains only the entries for which `f` returns `true`, then shrinks the
+    /// capacity of the map as much as possible.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map: FrozenMap<_, _> = (0..100).map(|i| (i, Box::new(i))).collect();
+    /// map.retain_and_shrink(|k, _| *k < 10);
+    /// assert_eq!(map.len(), 10);
+    /// assert!(map.capacity() < 100);
+    /// ```
+    pub fn retain_and_shrink<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        let map = self.map.get_mut();
+        map.retain(|k, v| f(k, v));
+        map.shrink_to_fit();
+        if let Some(order) = &mut self.order {
+            order.get_mut().retain(|k| map.contains_key(k));
+        }
     }
 }
 
@@ -75,21 +323,69 @@ impl<K: Eq + Hash, V: StableDeref, S: BuildHasher> FrozenMap<K, V, S> {
     // these should never return &K or &V
     // these should never delete any entries
     pub fn insert(&self, k: K, v: V) -> &V::Target {
+        self.insert_inner(k, v, true)
+    }
+
+    /// Inserts like `insert`, but only counts the lookup if `count_lookup` is
+    /// set, for methods which already counted it themselves.
+    fn insert_inner(&self, k: K, v: V, count_lookup: bool) -> &V::Target {
         assert!(!self.in_use.get());
         self.in_use.set(true);
         let ret = unsafe {
             let map = self.map.get();
-            &*(*map).entry(k).or_insert(v)
+            match (*map).entry(k) {
+                hash_map::Entry::Occupied(entry) => {
+                    if count_lookup {
+                        self.count(|stats| &stats.hits);
+                    }
+                    &**entry.into_mut()
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    if count_lookup {
+                        self.count(|stats| &stats.misses);
+                    }
+                    self.record_insert(entry.key());
+                    &**entry.insert(v)
+                }
+            }
         };
         self.in_use.set(false);
         ret
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// Like [`FrozenMap::insert`], but returns an error carrying back the key
+    /// and value instead of panicking if the map is already in use, for example
+    /// when called from the [`Eq`] or [`Hash`] implementation of a key.
     ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
-    /// the key type.
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// assert_eq!(map.try_get_or_insert(1, Box::new("a")), Ok(&"a"));
+    /// assert_eq!(map.try_get_or_insert(1, Box::new("b")), Ok(&"a"));
+    ///
+    /// // the map is in use while an entry is alive
+    /// let entry = map.entry(2);
+    /// let err = map.try_get_or_insert(3, Box::new("c")).unwrap_err();
+    /// assert_eq!(err.into_inner(), (3, Box::new("c")));
+    /// drop(entry);
+    /// ```
+    pub fn try_get_or_insert(&self, k: K, v: V) -> Result<&V::Target, ReentrancyError<(K, V)>> {
+        if self.in_use.get() {
+            return Err(ReentrancyError::new((k, v)));
+        }
+        Ok(self.insert(k, v))
+    }
+
+    /// Inserts every key-value pair of `iter` into the map, reserving space
+    /// from its size hint first.
+    ///
+    /// As with [`FrozenMap::insert`], existing values are never overwritten:
+    /// values whose key is already present, including duplicates within
+    /// `iter`, are dropped. The iterator is advanced without the map being in
+    /// use, so it may itself access the map.
     ///
     /// # Examples
     ///
@@ -97,62 +393,212 @@ impl<K: Eq + Hash, V: StableDeref, S: BuildHasher> FrozenMap<K, V, S> {
     /// use elsa::FrozenMap;
     ///
     /// let map = FrozenMap::new();
-    /// map.insert(1, Box::new("a"));
-    /// assert_eq!(map.get(&1), Some(&"a"));
-    /// assert_eq!(map.get(&2), None);
+    /// let a = map.insert(1, Box::new("a"));
+    /// map.extend(vec![(1, Box::new("x")), (2, Box::new("b")), (2, Box::new("y"))]);
+    /// assert_eq!(a, &"a");
+    /// assert_eq!(map.get(&2), Some(&"b"));
     /// ```
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V::Target>
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    ///
+    /// The key is only hashed once, whichever way the entry is then used.
+    ///
+    /// The map cannot be accessed while the entry is alive; doing so
+    /// will panic. This includes any value creation function passed to the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::map::Entry;
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// assert_eq!(map.entry(1).or_insert(Box::new("a")), &"a");
+    /// assert_eq!(map.entry(1).or_insert_with(|| unreachable!()), &"a");
+    /// assert_eq!(map.entry(2).or_insert_with_key(|k| Box::new(if *k == 2 { "b" } else { "c" })), &"b");
+    ///
+    /// match map.entry(1) {
+    ///     Entry::Occupied(entry) => assert_eq!(entry.get(), &"a"),
+    ///     Entry::Vacant(_) => unreachable!(),
+    /// };
+    /// ```
+    pub fn entry(&self, k: K) -> Entry<'_, K, V> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let entry = unsafe {
+            let map = self.map.get();
+            (*map).entry(k)
+        };
+        match entry {
+            hash_map::Entry::Occupied(entry) => {
+                self.count(|stats| &stats.hits);
+                Entry::Occupied(OccupiedEntry {
+                    entry: Some(entry),
+                    in_use: &self.in_use,
+                })
+            }
+            hash_map::Entry::Vacant(entry) => {
+                self.count(|stats| &stats.misses);
+                Entry::Vacant(VacantEntry {
+                    entry: Some(entry),
+                    in_use: &self.in_use,
+                    recorder: unsafe { self.recorder() },
+                })
+            }
+        }
+    }
+
+    /// Gets the entry for a borrowed form of the key, such as a `&str` for a
+    /// map keyed by `String`.
+    ///
+    /// Unlike [`FrozenMap::entry`], the key is only converted into an owned
+    /// `K` if the entry is vacant and a value is inserted. The map is not kept
+    /// in use while the entry is alive, so value creation functions may access
+    /// the map; if they insert the same key, that value is kept and returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::map::EntryRef;
+    /// use elsa::FrozenMap;
+    ///
+    /// let map: FrozenMap<String, Box<i32>> = FrozenMap::new();
+    /// assert_eq!(map.entry_ref("a").or_insert(Box::new(1)), &1);
+    /// assert_eq!(map.entry_ref("a").or_insert_with(|| unreachable!()), &1);
+    /// assert_eq!(map.entry_ref("bb").or_insert_with_key(|k| Box::new(k.len() as i32)), &2);
+    ///
+    /// match map.entry_ref("c") {
+    ///     EntryRef::Occupied(_) => unreachable!(),
+    ///     EntryRef::Vacant(entry) => assert_eq!(entry.key(), "c"),
+    /// };
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn entry_ref<'a, 'q, Q: ?Sized>(&'a self, k: &'q Q) -> EntryRef<'a, 'q, K, Q, V, S>
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ToOwned<Owned = K>,
+    {
+        match self.get(k) {
+            Some(value) => EntryRef::Occupied(value),
+            None => EntryRef::Vacant(VacantEntryRef { map: self, key: k }),
+        }
+    }
+
+    /// If the key exists in the map, returns a reference to the corresponding
+    /// value, otherwise inserts `V::default()` for that key, calls `init` on it
+    /// to populate it in place, and returns a reference to the new value.
+    ///
+    /// `init` is not called if the key is already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map: FrozenMap<i32, Vec<i32>> = FrozenMap::new();
+    /// assert_eq!(map.get_or_insert_default_then(1, |v| v.extend([1, 2, 3])), &[1, 2, 3]);
+    /// assert_eq!(map.get_or_insert_default_then(1, |_| unreachable!()), &[1, 2, 3]);
+    /// ```
+    pub fn get_or_insert_default_then<F>(&self, k: K, init: F) -> &V::Target
+    where
+        V: Default,
+        F: FnOnce(&mut V),
     {
         assert!(!self.in_use.get());
         self.in_use.set(true);
         let ret = unsafe {
             let map = self.map.get();
-            (*map).get(k).map(|x| &**x)
+            match (*map).entry(k) {
+                hash_map::Entry::Occupied(entry) => {
+                    self.count(|stats| &stats.hits);
+                    &**entry.into_mut()
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    self.count(|stats| &stats.misses);
+                    self.record_insert(entry.key());
+                    let v = entry.insert(V::default());
+                    init(v);
+                    &**v
+                }
+            }
         };
         self.in_use.set(false);
         ret
     }
 
-    /// Applies a function to the owner of the value corresponding to the key (if any).
+    /// If the key exists in the map, returns a reference to the corresponding
+    /// value, otherwise inserts `V::default()` for that key and returns a
+    /// reference to it.
+    ///
+    /// The default value is only created if the key is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map: FrozenMap<&str, Box<u32>> = FrozenMap::new();
+    /// assert_eq!(map.get_or_insert_default("a"), &0);
+    /// map.insert("b", Box::new(1));
+    /// assert_eq!(map.get_or_insert_default("b"), &1);
+    /// ```
+    pub fn get_or_insert_default(&self, k: K) -> &V::Target
+    where
+        V: Default,
+    {
+        self.get_or_insert_default_then(k, |_| ())
+    }
+
+    /// If the key exists in the map, returns a reference to the corresponding
+    /// value, otherwise inserts a new entry in the map for an owned copy of the
+    /// key and the value returned by `make`, and returns a reference to it.
+    ///
+    /// The key is only converted to its owned form when it is missing, so a
+    /// `FrozenMap<String, _>` can be queried with a `&str` without allocating
+    /// on a hit.
     ///
     /// The key may be any borrowed form of the map's key type, but
     /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
     /// the key type.
     ///
+    /// `make` is called without the map being in use, so it may itself access
+    /// the map. If it inserts the same key, that value is kept and returned.
+    ///
     /// # Examples
     ///
     /// ```
     /// use elsa::FrozenMap;
     ///
-    /// let map = FrozenMap::new();
-    /// map.insert(1, Box::new("a"));
-    /// assert_eq!(map.map_get(&1, Clone::clone), Some(Box::new("a")));
-    /// assert_eq!(map.map_get(&2, Clone::clone), None);
+    /// let map: FrozenMap<String, Box<i32>> = FrozenMap::new();
+    /// assert_eq!(map.get_or_insert_with_key_ref("a", || Box::new(1)), &1);
+    /// assert_eq!(map.get_or_insert_with_key_ref("a", || unreachable!()), &1);
     /// ```
-    pub fn map_get<Q: ?Sized, T, F>(&self, k: &Q, f: F) -> Option<T>
+    pub fn get_or_insert_with_key_ref<Q: ?Sized, F>(&self, k: &Q, make: F) -> &V::Target
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
-        F: FnOnce(&V) -> T,
+        Q: Hash + Eq + ToOwned<Owned = K>,
+        F: FnOnce() -> V,
     {
-        assert!(!self.in_use.get());
-        self.in_use.set(true);
-        let ret = unsafe {
-            let map = self.map.get();
-            (*map).get(k).map(f)
-        };
-        self.in_use.set(false);
-        ret
+        if let Some(v) = self.get(k) {
+            return v;
+        }
+        self.insert_inner(k.to_owned(), make(), false)
     }
-}
 
-impl<K, V, S> FrozenMap<K, V, S> {
-    /// Collects the contents of this map into a vector of tuples.
+    /// Returns the value for the key if it exists, otherwise calls `make` to
+    /// insert one, but only while the map holds fewer than `limit` entries.
+    /// Returns `None` if the key is missing and the map is full.
     ///
-    /// The order of the entries is as if iterating a [`HashMap`] (stochastic).
+    /// `make` is called without the map being in use, so it may itself access
+    /// the map. If it inserts the same key, that value is kept and returned;
+    /// if it fills the map up, its value is dropped and `None` returned.
     ///
     /// # Examples
     ///
@@ -160,22 +606,1344 @@ impl<K, V, S> FrozenMap<K, V, S> {
     /// use elsa::FrozenMap;
     ///
     /// let map = FrozenMap::new();
-    /// map.insert(1, Box::new("a"));
-    /// map.insert(2, Box::new("b"));
-    /// let mut tuple_vec = map.into_tuple_vec();
-    /// tuple_vec.sort();
-    ///
-    /// assert_eq!(tuple_vec, vec![(1, Box::new("a")), (2, Box::new("b"))]);
+    /// assert_eq!(map.get_or_insert_with_limit(1, 1, || Box::new("a")), Some(&"a"));
+    /// assert_eq!(map.get_or_insert_with_limit(2, 1, || Box::new("b")), None);
+    /// assert_eq!(map.get_or_insert_with_limit(1, 1, || unreachable!()), Some(&"a"));
+    /// assert_eq!(map.len(), 1);
     /// ```
-    pub fn into_tuple_vec(self) -> Vec<(K, V)> {
-        self.map.into_inner().into_iter().collect::<Vec<_>>()
+    pub fn get_or_insert_with_limit<F>(&self, k: K, limit: usize, make: F) -> Option<&V::Target>
+    where
+        F: FnOnce() -> V,
+    {
+        if let Some(v) = self.get(&k) {
+            return Some(v);
+        }
+        if self.len() >= limit {
+            return None;
+        }
+        let v = make();
+        if !self.contains_key(&k) && self.len() >= limit {
+            return None;
+        }
+        Some(self.insert_inner(k, v, false))
     }
 
-    pub fn into_map(self) -> HashMap<K, V, S> {
-        self.map.into_inner()
+    /// Returns the value for the key if it exists, otherwise inserts a clone
+    /// of the value `fallback` has for it, or, if `fallback` does not have the
+    /// key either, the value returned by `make`.
+    ///
+    /// Promoting from `fallback` costs a clone of the whole `V`, for example
+    /// of the `Box`ed value rather than of a pointer to it; share values
+    /// through an `Rc` or `Arc` to make it cheap. `make` is only called if
+    /// neither map has the key, and without either map being in use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let l2 = FrozenMap::new();
+    /// l2.insert(1, Box::new("a"));
+    /// let l1 = FrozenMap::new();
+    /// assert_eq!(l1.get_or_insert_from(1, &l2, || unreachable!()), &"a");
+    /// assert_eq!(l1.get_or_insert_from(2, &l2, || Box::new("b")), &"b");
+    /// assert_eq!(l1.len(), 2);
+    /// assert_eq!(l2.len(), 1);
+    /// ```
+    pub fn get_or_insert_from<S2, F>(
+        &self,
+        k: K,
+        fallback: &FrozenMap<K, V, S2>,
+        make: F,
+    ) -> &V::Target
+    where
+        V: Clone,
+        S2: BuildHasher,
+        F: FnOnce() -> V,
+    {
+        if let Some(v) = self.get(&k) {
+            return v;
+        }
+        let v = match fallback.map_get(&k, V::clone) {
+            Some(v) => v,
+            None => make(),
+        };
+        self.insert_inner(k, v, false)
+    }
+
+    /// Returns the value of the key inserted `index`-th, for maps created with
+    /// [`FrozenMap::with_indexing`].
+    ///
+    /// Removing a key with [`FrozenMap::remove`] shifts the index of every key
+    /// inserted after it down by one, and so does removing it through
+    /// `as_mut`. Keys inserted through `as_mut` get indices after all others.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map was not created with [`FrozenMap::with_indexing`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::with_indexing();
+    /// map.insert("b", Box::new(2));
+    /// map.insert("a", Box::new(1));
+    /// assert_eq!(map.get_index(0), Some(&2));
+    /// assert_eq!(map.get_index(1), Some(&1));
+    /// assert_eq!(map.get_index(2), None);
+    ///
+    /// map.remove("b");
+    /// assert_eq!(map.get_index(0), Some(&1));
+    /// assert_eq!(map.insert_full("c", Box::new(3)), (1, &3));
+    ///
+    /// map.as_mut().remove("a");
+    /// map.as_mut().insert("d", Box::new(4));
+    /// assert_eq!(map.get_index(0), Some(&3));
+    /// assert_eq!(map.get_index(1), Some(&4));
+    /// assert_eq!(map.get_index(2), None);
+    /// ```
+    pub fn get_index(&self, index: usize) -> Option<&V::Target> {
+        let order = self
+            .order
+            .as_ref()
+            .expect("FrozenMap::get_index requires a map created with with_indexing");
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let order = &mut *order.get();
+            let map = &*self.map.get();
+            order.sync(map);
+            order.keys.get(index).and_then(|k| map.get(k)).map(|v| &**v)
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Like [`FrozenMap::insert`], but also returns the index of the key, for
+    /// maps created with [`FrozenMap::with_indexing`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map was not created with [`FrozenMap::with_indexing`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::with_indexing();
+    /// assert_eq!(map.insert_full("a", Box::new(1)), (0, &1));
+    /// assert_eq!(map.insert_full("b", Box::new(2)), (1, &2));
+    /// assert_eq!(map.insert_full("a", Box::new(3)), (0, &1));
+    /// ```
+    pub fn insert_full(&self, k: K, v: V) -> (usize, &V::Target) {
+        let order = self
+            .order
+            .as_ref()
+            .expect("FrozenMap::insert_full requires a map created with with_indexing");
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let (index, existing) = unsafe {
+            let order = &mut *order.get();
+            let map = &*self.map.get();
+            order.sync(map);
+            match map.get(&k) {
+                Some(v) => (
+                    order.keys.iter().position(|key| *key == k).unwrap(),
+                    Some(&**v),
+                ),
+                None => (order.keys.len(), None),
+            }
+        };
+        self.in_use.set(false);
+        match existing {
+            Some(existing) => {
+                self.count(|stats| &stats.hits);
+                (index, existing)
+            }
+            None => (index, self.insert(k, v)),
+        }
+    }
+
+    /// Reserves the key for a value that will be provided later through the
+    /// returned [`Slot`], or returns `None` if the key already has a value or
+    /// is already reserved.
+    ///
+    /// Unlike an [`Entry`], a slot does not keep the map in use, so the map
+    /// may be accessed freely while the value is being built, including to
+    /// reserve further keys. Until the slot is filled the key has no value:
+    /// lookups return `None` and reserving it again fails. Dropping the slot
+    /// without filling it releases the key.
+    ///
+    /// Reservations are advisory: they only keep other reservations out, so
+    /// [`FrozenMap::insert`] and the other inserting methods may still give
+    /// the key a value, which [`Slot::fill`] then keeps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// let slot = map.reserve_slot(1).unwrap();
+    /// assert!(map.reserve_slot(1).is_none());
+    /// assert_eq!(map.get(&1), None);
+    /// map.insert(2, Box::new("b"));
+    /// assert_eq!(slot.fill(Box::new("a")), &"a");
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    ///
+    /// // an unfilled slot releases its key when dropped
+    /// drop(map.reserve_slot(3).unwrap());
+    /// assert!(map.reserve_slot(3).is_some());
+    ///
+    /// // also while the map is in use
+    /// let mut slot = map.reserve_slot(4);
+    /// map.for_each(|_, _| drop(slot.take()));
+    /// assert!(map.reserve_slot(4).is_some());
+    /// ```
+    pub fn reserve_slot(&self, k: K) -> Option<Slot<'_, K, V, S>>
+    where
+        K: Clone,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let reserved = unsafe {
+            let map = self.map.get();
+            let pending = (*self.pending.get()).get_or_insert_with(Box::default);
+            pending.release_deferred();
+            !(*map).contains_key(&k) && pending.reserved.insert(k.clone())
+        };
+        self.in_use.set(false);
+        if reserved {
+            Some(Slot {
+                map: self,
+                key: Some(k),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// If the key exists in the map, returns a reference to the corresponding
+    /// value, otherwise computes it with `make`, inserts it and returns a
+    /// reference to it.
+    ///
+    /// `make` is called without the map being in use, so it may look up and
+    /// compute the values of other keys through this method, as memoized
+    /// recursive functions do. The key is reserved as with
+    /// [`FrozenMap::reserve_slot`] while its value is computed; needing it
+    /// again in the meantime is a cycle, reported as a [`CycleError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// fn fib(memo: &FrozenMap<u64, Box<u64>>, n: u64) -> u64 {
+    ///     *memo
+    ///         .get_or_insert_recursive(n, |&n| {
+    ///             Box::new(if n < 2 { n } else { fib(memo, n - 1) + fib(memo, n - 2) })
+    ///         })
+    ///         .unwrap()
+    /// }
+    ///
+    /// let memo = FrozenMap::new();
+    /// assert_eq!(fib(&memo, 50), 12586269025);
+    ///
+    /// let cyclic = memo.get_or_insert_recursive(100, |_| {
+    ///     let err = memo.get_or_insert_recursive(100, |_| unreachable!()).unwrap_err();
+    ///     assert_eq!(err.into_key(), 100);
+    ///     Box::new(0)
+    /// });
+    /// assert_eq!(cyclic, Ok(&0));
+    ///
+    /// // a panic in `make` releases the keys being computed
+    /// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    ///     memo.get_or_insert_recursive(200, |_| {
+    ///         memo.get_or_insert_recursive(201, |_| panic!()).ok();
+    ///         Box::new(0)
+    ///     })
+    /// }));
+    /// assert!(result.is_err());
+    /// assert_eq!(memo.get_or_insert_recursive(200, |_| Box::new(1)), Ok(&1));
+    /// assert_eq!(memo.get_or_insert_recursive(201, |_| Box::new(2)), Ok(&2));
+    /// ```
+    pub fn get_or_insert_recursive<F>(&self, k: K, make: F) -> Result<&V::Target, CycleError<K>>
+    where
+        K: Clone,
+        F: FnOnce(&K) -> V,
+    {
+        if let Some(existing) = self.get(&k) {
+            return Ok(existing);
+        }
+        match self.reserve_slot(k.clone()) {
+            Some(slot) => {
+                let value = make(slot.key());
+                Ok(slot.fill(value))
+            }
+            None => Err(CycleError::new(k)),
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V::Target>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get(k).map(|x| &**x)
+        };
+        self.in_use.set(false);
+        if ret.is_some() {
+            self.count(|stats| &stats.hits);
+        } else {
+            self.count(|stats| &stats.misses);
+        }
+        ret
+    }
+
+    /// Applies a function to the owner of the value corresponding to the key (if any).
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.map_get(&1, Clone::clone), Some(Box::new("a")));
+    /// assert_eq!(map.map_get(&2, Clone::clone), None);
+    /// ```
+    pub fn map_get<Q: ?Sized, T, F>(&self, k: &Q, f: F) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+        F: FnOnce(&V) -> T,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get(k).map(f)
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Returns the number of elements the map can hold without reallocating.
+    ///
+    /// Reallocation moves the keys and the values' owners, but not the
+    /// [`StableDeref`] targets that references point to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map: FrozenMap<i32, Box<i32>> = FrozenMap::new();
+    /// map.reserve(100);
+    /// assert!(map.capacity() >= 100);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).capacity()
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted.
+    ///
+    /// This may rehash the map, but since the values are [`StableDeref`], and
+    /// no entries are removed, previously returned references stay valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// let a = map.insert(1, Box::new("a"));
+    /// map.reserve(100);
+    /// assert_eq!(a, &"a");
+    /// ```
+    pub fn reserve(&self, additional: usize) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            let map = self.map.get();
+            (*map).reserve(additional);
+        }
+        self.in_use.set(false);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements to be
+    /// inserted, returning an error instead of aborting if the allocation fails.
+    ///
+    /// As with [`FrozenMap::reserve`], previously returned references stay valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map: FrozenMap<i32, Box<i32>> = FrozenMap::new();
+    /// assert!(map.try_reserve(100).is_ok());
+    /// assert!(map.try_reserve(usize::MAX).is_err());
+    /// ```
+    pub fn try_reserve(&self, additional: usize) -> Result<(), TryReserveError> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).try_reserve(additional)
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Shrinks the capacity of the map as much as possible.
+    ///
+    /// As with [`FrozenMap::reserve`], previously returned references stay valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.reserve(100);
+    /// let a = map.insert(1, Box::new("a"));
+    /// map.shrink_to_fit();
+    /// assert_eq!(a, &"a");
+    /// ```
+    pub fn shrink_to_fit(&self) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            let map = self.map.get();
+            (*map).shrink_to_fit();
+        }
+        self.in_use.set(false);
+    }
+
+    /// Returns an iterator over the entries of the map, in arbitrary order.
+    ///
+    /// Keys are moved around in a [`HashMap`] when it rehashes, so like values
+    /// they have to be [`StableDeref`] to be handed out by reference.
+    ///
+    /// The map cannot be accessed while the iterator is alive; doing so
+    /// will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(Box::new(1), Box::new("a"));
+    /// map.insert(Box::new(2), Box::new("b"));
+    /// let mut entries = map.iter().collect::<Vec<_>>();
+    /// entries.sort();
+    /// assert_eq!(entries, vec![(&1, &"a"), (&2, &"b")]);
+    /// ```
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K::Target, &V::Target)>
+    where
+        K: StableDeref,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let iter = unsafe { (*self.map.get()).iter() };
+        InUseIter {
+            iter: iter.map(|(k, v)| (&**k, &**v)),
+            in_use: &self.in_use,
+        }
+    }
+
+    /// Calls `f` on every entry of the map, in arbitrary order.
+    ///
+    /// Unlike [`FrozenMap::iter`], keys only need to be borrowed for the duration
+    /// of the call, so they don't have to be [`StableDeref`].
+    ///
+    /// The map cannot be accessed from `f`; doing so will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// let mut entries = Vec::new();
+    /// map.for_each(|k, v| entries.push(format!("{}={}", k, v)));
+    /// entries.sort();
+    /// assert_eq!(entries, vec!["1=a", "2=b"]);
+    /// ```
+    pub fn for_each<F: FnMut(&K, &V::Target)>(&self, mut f: F) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            let map = self.map.get();
+            for (k, v) in (*map).iter() {
+                f(k, v);
+            }
+        }
+        self.in_use.set(false);
+    }
+
+    /// Returns `true` if any key of the map has a value equal to `value`.
+    ///
+    /// This scans through all the values, so it takes time linear in the
+    /// size of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert!(map.contains_value(&"a"));
+    /// assert!(!map.contains_value(&"b"));
+    /// ```
+    pub fn contains_value(&self, value: &V::Target) -> bool
+    where
+        V::Target: PartialEq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).values().any(|v| **v == *value)
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenMap<K, Arc<V>, S> {
+    /// Returns a clone of the [`Arc`] corresponding to the key.
+    ///
+    /// This only bumps the reference count, unlike cloning the value
+    /// behind it, and the returned `Arc` is not tied to a borrow of the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::sync::Arc;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Arc::new("a"));
+    /// let a = map.get_arc(&1).unwrap();
+    /// assert_eq!(Arc::strong_count(&a), 2);
+    /// assert_eq!(map.get_arc(&2), None);
+    /// ```
+    pub fn get_arc<Q: ?Sized>(&self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map_get(k, Arc::clone)
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher> FrozenMap<K, Box<dyn Any>, S> {
+    /// Returns a reference to the value corresponding to the key, if it is
+    /// present and of type `T`.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::any::Any;
+    ///
+    /// let map: FrozenMap<&str, Box<dyn Any>> = FrozenMap::new();
+    /// map.insert("answer", Box::new(42_u32));
+    /// assert_eq!(map.get_as::<u32, _>("answer"), Some(&42));
+    /// assert_eq!(map.get_as::<i64, _>("answer"), None);
+    /// assert_eq!(map.get_as::<u32, _>("question"), None);
+    /// ```
+    pub fn get_as<T: Any, Q: ?Sized>(&self, k: &Q) -> Option<&T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.get(k)?.downcast_ref()
+    }
+
+    /// Returns a reference to the value corresponding to the key if it is
+    /// present, otherwise inserts the result of `make` and returns a reference
+    /// to it. Either way, returns `None` if the value is not of type `T`.
+    ///
+    /// `make` is called without the map being in use, so it may access the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::any::Any;
+    ///
+    /// let map: FrozenMap<&str, Box<dyn Any>> = FrozenMap::new();
+    /// assert_eq!(map.get_or_insert_as("name", || String::from("elsa")), Some(&"elsa".to_string()));
+    /// assert_eq!(map.get_or_insert_as::<String, _>("name", || unreachable!()), Some(&"elsa".to_string()));
+    /// assert_eq!(map.get_or_insert_as("name", || 0_u8), None);
+    /// ```
+    pub fn get_or_insert_as<T: Any, F: FnOnce() -> T>(&self, k: K, make: F) -> Option<&T> {
+        match self.get(&k) {
+            Some(existing) => existing.downcast_ref(),
+            None => self.insert_inner(k, Box::new(make()), false).downcast_ref(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenMap<K, Weak<V>, S> {
+    /// If the key maps to a [`Weak`] that can still be upgraded, returns the
+    /// upgraded [`Arc`], otherwise calls `make` and stores a [`Weak`] to its
+    /// result, returning it.
+    ///
+    /// An entry whose value has been dropped is treated as missing and
+    /// replaced. This is sound because no references into a `Weak` value are
+    /// ever handed out.
+    ///
+    /// `make` is called without the map being in use, so it may itself access
+    /// the map. If it inserts a live value for the same key, that value is kept
+    /// and returned.
+    ///
+    /// Dead entries are not removed automatically; they accumulate until pruned,
+    /// for example with `map.as_mut().retain(|_, v| v.strong_count() > 0)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::sync::{Arc, Weak};
+    ///
+    /// let map: FrozenMap<i32, Weak<String>> = FrozenMap::new();
+    /// let a = map.get_or_insert_weak(1, || Arc::new("a".to_string()));
+    /// assert!(Arc::ptr_eq(&a, &map.get_or_insert_weak(1, || unreachable!())));
+    /// drop(a);
+    /// let b = map.get_or_insert_weak(1, || Arc::new("b".to_string()));
+    /// assert_eq!(*b, "b");
+    /// ```
+    pub fn get_or_insert_weak<F>(&self, k: K, make: F) -> Arc<V>
+    where
+        F: FnOnce() -> Arc<V>,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let existing = unsafe {
+            let map = self.map.get();
+            (*map).get(&k).and_then(Weak::upgrade)
+        };
+        self.in_use.set(false);
+        if let Some(existing) = existing {
+            self.count(|stats| &stats.hits);
+            return existing;
+        }
+        self.count(|stats| &stats.misses);
+
+        let v = make();
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            match (*map).entry(k) {
+                hash_map::Entry::Occupied(mut entry) => match entry.get().upgrade() {
+                    Some(existing) => existing,
+                    None => {
+                        self.recorder().record_reinsert(entry.key());
+                        entry.insert(Arc::downgrade(&v));
+                        v
+                    }
+                },
+                hash_map::Entry::Vacant(entry) => {
+                    self.record_insert(entry.key());
+                    entry.insert(Arc::downgrade(&v));
+                    v
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K, V, S> FrozenMap<K, V, S> {
+    /// Creates an empty map which will use the given hasher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let map = FrozenMap::with_hasher(RandomState::new());
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::from(HashMap::with_hasher(hasher))
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let len = unsafe {
+            let map = self.map.get();
+            (*map).len()
+        };
+        self.in_use.set(false);
+        len
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// assert_eq!(map.is_empty(), true);
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the lookup counts of a map created with [`FrozenMap::with_stats`],
+    /// or `None` for other maps.
+    ///
+    /// [`FrozenMap::get`] and every method which inserts a value for a missing
+    /// key, such as [`FrozenMap::insert`], the `get_or_insert` family and
+    /// [`FrozenMap::entry`], count each lookup once as a hit or a miss, and
+    /// each value they then insert as an insertion. A [`Slot`] counts its
+    /// insertion when filled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::map::CacheStats;
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::with_stats();
+    /// map.get(&1);
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(1, Box::new("b"));
+    /// map.get(&1);
+    /// assert_eq!(map.stats(), Some(CacheStats { hits: 2, misses: 2, inserts: 1 }));
+    ///
+    /// map.entry(2).or_insert(Box::new("b"));
+    /// map.entry(2).or_insert(Box::new("c"));
+    /// map.get_or_insert_default(3);
+    /// map.get_or_insert_with_key_ref(&3, || unreachable!());
+    /// assert_eq!(map.stats(), Some(CacheStats { hits: 4, misses: 4, inserts: 3 }));
+    ///
+    /// let map: FrozenMap<i32, Box<&str>> = FrozenMap::new();
+    /// assert_eq!(map.stats(), None);
+    /// ```
+    pub fn stats(&self) -> Option<CacheStats> {
+        self.stats.as_ref().map(|stats| stats.snapshot())
+    }
+
+    fn count(&self, counter: impl FnOnce(&StatsCounters) -> &AtomicU64) {
+        if let Some(stats) = &self.stats {
+            counter(stats).fetch_add(1, atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Borrows what the opt-in features need to record insertions.
+    ///
+    /// Must only be called while the map is in use, and the result must not
+    /// outlive that.
+    unsafe fn recorder(&self) -> Recorder<'_, K> {
+        Recorder {
+            stats: self.stats.as_deref(),
+            order: self.order.as_deref(),
+            miss_hook: (*self.miss_hook.get()).as_deref(),
+        }
+    }
+
+    /// Records a key about to be inserted with a new value after a miss.
+    ///
+    /// Must only be called while the map is in use.
+    unsafe fn record_insert(&self, key: &K) {
+        self.recorder().record_insert(key)
+    }
+
+    /// Registers a callback which is called with every key a new value is
+    /// inserted for after a lookup missed, replacing any previously registered
+    /// one. This covers all the inserting methods, including entries and
+    /// slots.
+    ///
+    /// The map is in use while the callback runs, so it must not access the
+    /// map; doing so will panic. Clones of the map do not keep the callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let loads = Arc::new(AtomicUsize::new(0));
+    /// let map = FrozenMap::new();
+    /// let counter = loads.clone();
+    /// map.on_miss(move |_: &i32| {
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(1, Box::new("b"));
+    /// map.get(&2);
+    /// assert_eq!(loads.load(Ordering::Relaxed), 1);
+    ///
+    /// map.entry(2).or_insert(Box::new("b"));
+    /// map.entry(2).or_insert(Box::new("c"));
+    /// map.get_or_insert_default(3);
+    /// map.get_or_insert_default(3);
+    /// assert_eq!(loads.load(Ordering::Relaxed), 3);
+    /// ```
+    pub fn on_miss<F: Fn(&K) + Send + 'static>(&self, cb: F) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            *self.miss_hook.get() = Some(Box::new(MissHook(Box::new(cb))));
+        }
+        self.in_use.set(false);
+    }
+
+    /// Collects the contents of this map into a vector of tuples.
+    ///
+    /// The order of the entries is as if iterating a [`HashMap`] (stochastic).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// let mut tuple_vec = map.into_tuple_vec();
+    /// tuple_vec.sort();
+    ///
+    /// assert_eq!(tuple_vec, vec![(1, Box::new("a")), (2, Box::new("b"))]);
+    /// ```
+    pub fn into_tuple_vec(self) -> Vec<(K, V)> {
+        self.map.into_inner().into_iter().collect::<Vec<_>>()
+    }
+
+    pub fn into_map(self) -> HashMap<K, V, S> {
+        self.map.into_inner()
+    }
+
+    /// Freezes a standard [`HashMap`]. This is the same as the [`From`] conversion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut std = HashMap::new();
+    /// std.insert(1, Box::new("a"));
+    /// let frozen = FrozenMap::from_std(std);
+    /// assert_eq!(frozen.get(&1), Some(&"a"));
+    /// let std: HashMap<_, _> = frozen.into_std();
+    /// assert_eq!(std.len(), 1);
+    /// ```
+    pub fn from_std(map: HashMap<K, V, S>) -> Self {
+        Self::from(map)
+    }
+
+    /// Unfreezes into the standard [`HashMap`].
+    pub fn into_std(self) -> HashMap<K, V, S> {
+        self.into_map()
+    }
+
+    /// Clears the map, returning all key-value pairs as an iterator. Keeps the
+    /// allocated memory for reuse.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// let mut drained = map.drain().collect::<Vec<_>>();
+    /// drained.sort();
+    ///
+    /// assert_eq!(drained, vec![(1, Box::new("a")), (2, Box::new("b"))]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        if let Some(order) = &mut self.order {
+            order.get_mut().clear();
+        }
+        self.map.get_mut().drain()
+    }
+
+    /// Returns an iterator over mutable references to the values of the map,
+    /// in arbitrary order.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut map = FrozenMap::new();
+    /// map.insert(1, Box::new(1));
+    /// map.insert(2, Box::new(2));
+    /// for v in map.values_mut() {
+    ///     **v += 10;
+    /// }
+    /// assert_eq!(map.get(&1), Some(&11));
+    /// assert_eq!(map.get(&2), Some(&12));
+    /// ```
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        self.map.get_mut().values_mut()
+    }
+
+    /// Inserts clones of all the entries of `other` into the map.
+    ///
+    /// When a key is in both maps, `resolve` is called with the existing value
+    /// and the one from `other`, and the new value replaces the existing one if
+    /// it returns `true`.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents that may be replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let mut base = FrozenMap::new();
+    /// base.insert(1, Box::new("a"));
+    /// base.insert(2, Box::new("b"));
+    /// let overrides = FrozenMap::new();
+    /// overrides.insert(2, Box::new("x"));
+    /// overrides.insert(3, Box::new("c"));
+    /// base.merge(&overrides, |_existing, _new| true);
+    /// assert_eq!(base.get(&1), Some(&"a"));
+    /// assert_eq!(base.get(&2), Some(&"x"));
+    /// assert_eq!(base.get(&3), Some(&"c"));
+    /// ```
+    pub fn merge<S2>(
+        &mut self,
+        other: &FrozenMap<K, V, S2>,
+        mut resolve: impl FnMut(&V, &V) -> bool,
+    ) where
+        K: Eq + Hash + Clone,
+        V: Clone,
+        S: BuildHasher,
+    {
+        assert!(!other.in_use.get());
+        other.in_use.set(true);
+        let entries: Vec<(K, V)> = unsafe {
+            let other = other.map.get();
+            (*other)
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        };
+        other.in_use.set(false);
+
+        let map = self.map.get_mut();
+        let mut order = self.order.as_mut().map(|order| order.get_mut());
+        map.reserve(entries.len());
+        for (k, v) in entries {
+            match map.entry(k) {
+                hash_map::Entry::Occupied(mut entry) => {
+                    if resolve(entry.get(), &v) {
+                        entry.insert(v);
+                    }
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    if let Some(order) = &mut order {
+                        order.push(entry.key());
+                    }
+                    entry.insert(v);
+                }
+            }
+        }
+    }
+
+    // TODO add more
+}
+
+/// A view into a single entry in a [`FrozenMap`], obtained via [`FrozenMap::entry`].
+///
+/// The map is marked as in use for as long as the entry is alive.
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Eq + Hash, V: StableDeref> Entry<'a, K, V> {
+    /// Returns a reference to the existing value, or inserts `default` and
+    /// returns a reference to it.
+    pub fn or_insert(self, default: V) -> &'a V::Target {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Returns a reference to the existing value, or inserts the result of `default`
+    /// and returns a reference to it.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a V::Target {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Returns a reference to the existing value, or inserts the result of `default`
+    /// called with the entry's key and returns a reference to it.
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a V::Target {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Returns a reference to the entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+/// An occupied entry in a [`FrozenMap`]. Part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V> {
+    // only `None` once consumed
+    entry: Option<hash_map::OccupiedEntry<'a, K, V>>,
+    in_use: &'a Cell<bool>,
+}
+
+impl<'a, K, V: StableDeref> OccupiedEntry<'a, K, V> {
+    /// Returns a reference to the entry's key.
+    pub fn key(&self) -> &K {
+        self.entry.as_ref().unwrap().key()
+    }
+
+    /// Returns a reference to the entry's value.
+    pub fn get(&self) -> &V::Target {
+        self.entry.as_ref().unwrap().get()
+    }
+
+    /// Converts the entry into a reference to its value, which lives as long
+    /// as the map.
+    pub fn into_ref(mut self) -> &'a V::Target {
+        self.entry.take().unwrap().into_mut()
+    }
+}
+
+impl<'a, K, V> Drop for OccupiedEntry<'a, K, V> {
+    fn drop(&mut self) {
+        self.in_use.set(false);
+    }
+}
+
+/// A vacant entry in a [`FrozenMap`]. Part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V> {
+    // only `None` once consumed
+    entry: Option<hash_map::VacantEntry<'a, K, V>>,
+    in_use: &'a Cell<bool>,
+    recorder: Recorder<'a, K>,
+}
+
+impl<'a, K: Eq + Hash, V: StableDeref> VacantEntry<'a, K, V> {
+    /// Returns a reference to the key that would be used when inserting.
+    pub fn key(&self) -> &K {
+        self.entry.as_ref().unwrap().key()
+    }
+
+    /// Takes ownership of the key, leaving the map untouched.
+    pub fn into_key(mut self) -> K {
+        self.entry.take().unwrap().into_key()
+    }
+
+    /// Inserts the value into the map, returning a reference to it which lives
+    /// as long as the map.
+    pub fn insert(mut self, value: V) -> &'a V::Target {
+        let entry = self.entry.take().unwrap();
+        unsafe { self.recorder.record_insert(entry.key()) };
+        entry.insert(value)
+    }
+}
+
+impl<'a, K, V> Drop for VacantEntry<'a, K, V> {
+    fn drop(&mut self) {
+        self.in_use.set(false);
+    }
+}
+
+/// A view into a single entry in a [`FrozenMap`] looked up by a borrowed key,
+/// obtained via [`FrozenMap::entry_ref`].
+///
+/// Unlike [`Entry`], this does not keep the map in use.
+pub enum EntryRef<'a, 'q, K, Q: ?Sized, V: StableDeref, S> {
+    Occupied(&'a V::Target),
+    Vacant(VacantEntryRef<'a, 'q, K, Q, V, S>),
+}
+
+impl<'a, 'q, K, Q: ?Sized, V, S> EntryRef<'a, 'q, K, Q, V, S>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Hash + Eq + ToOwned<Owned = K>,
+    V: StableDeref,
+    S: BuildHasher,
+{
+    /// Returns a reference to the existing value, or inserts `default` and
+    /// returns a reference to it.
+    pub fn or_insert(self, default: V) -> &'a V::Target {
+        match self {
+            EntryRef::Occupied(value) => value,
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Returns a reference to the existing value, or inserts the result of `default`
+    /// and returns a reference to it.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a V::Target {
+        match self {
+            EntryRef::Occupied(value) => value,
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Returns a reference to the existing value, or inserts the result of `default`
+    /// called with the borrowed key and returns a reference to it.
+    pub fn or_insert_with_key<F: FnOnce(&Q) -> V>(self, default: F) -> &'a V::Target {
+        match self {
+            EntryRef::Occupied(value) => value,
+            EntryRef::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+}
+
+/// A vacant entry in a [`FrozenMap`]. Part of the [`EntryRef`] enum.
+pub struct VacantEntryRef<'a, 'q, K, Q: ?Sized, V, S> {
+    map: &'a FrozenMap<K, V, S>,
+    key: &'q Q,
+}
+
+impl<'a, 'q, K, Q: ?Sized, V, S> VacantEntryRef<'a, 'q, K, Q, V, S>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Hash + Eq + ToOwned<Owned = K>,
+    V: StableDeref,
+    S: BuildHasher,
+{
+    /// Returns the borrowed key that would be used when inserting.
+    pub fn key(&self) -> &'q Q {
+        self.key
+    }
+
+    /// Converts the key into an owned `K` and inserts the value into the map,
+    /// returning a reference to it which lives as long as the map.
+    pub fn insert(self, value: V) -> &'a V::Target {
+        // `entry_ref` already counted the miss
+        self.map.insert_inner(self.key.to_owned(), value, false)
+    }
+}
+
+/// A key reserved in a [`FrozenMap`] by [`FrozenMap::reserve_slot`],
+/// waiting for its value.
+///
+/// The key is released if the slot is dropped without being filled.
+pub struct Slot<'a, K: Eq + Hash, V, S> {
+    map: &'a FrozenMap<K, V, S>,
+    // only `None` once filled
+    key: Option<K>,
+}
+
+impl<'a, K: Eq + Hash, V, S> Slot<'a, K, V, S> {
+    /// Returns a reference to the reserved key.
+    pub fn key(&self) -> &K {
+        self.key.as_ref().unwrap()
+    }
+}
+
+impl<'a, K: Eq + Hash, V: StableDeref, S: BuildHasher> Slot<'a, K, V, S> {
+    /// Inserts the value for the reserved key, returning a reference to it
+    /// which lives as long as the map.
+    ///
+    /// If a value was inserted for the key by other means while the slot was
+    /// alive, that value is kept and returned, and `value` is dropped.
+    pub fn fill(mut self, value: V) -> &'a V::Target {
+        let key = self.key.take().unwrap();
+        let map = self.map;
+        assert!(!map.in_use.get());
+        map.in_use.set(true);
+        let ret = unsafe {
+            // allocated by the `reserve_slot` which created this slot
+            if let Some(pending) = &mut *map.pending.get() {
+                pending.release_deferred();
+                pending.reserved.remove(&key);
+            }
+            match (*map.map.get()).entry(key) {
+                hash_map::Entry::Occupied(entry) => &**entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => {
+                    map.record_insert(entry.key());
+                    &**entry.insert(value)
+                }
+            }
+        };
+        map.in_use.set(false);
+        ret
+    }
+}
+
+impl<'a, K: Eq + Hash, V, S> Drop for Slot<'a, K, V, S> {
+    fn drop(&mut self) {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => return,
+        };
+        let map = self.map;
+        unsafe {
+            if map.in_use.get() {
+                // The slot is dropped while the map is in use, typically when
+                // unwinding out of a closure the map was running. Releasing
+                // needs the key's `Hash` and `Eq`, so leave it to the next
+                // operation on the reservations rather than panicking again.
+                if let Some(pending) = &mut *map.pending.get() {
+                    pending.deferred.push(key);
+                }
+                return;
+            }
+            map.in_use.set(true);
+            if let Some(pending) = &mut *map.pending.get() {
+                pending.release_deferred();
+                pending.reserved.remove(&key);
+            }
+            map.in_use.set(false);
+        }
+    }
+}
+
+/// The keys reserved by the live slots of a map
+#[derive(Debug)]
+struct Pending<K> {
+    reserved: HashSet<K>,
+    /// Keys of slots dropped while the map was in use, still to be removed
+    /// from `reserved`
+    deferred: Vec<K>,
+}
+
+impl<K> Default for Pending<K> {
+    fn default() -> Self {
+        Pending {
+            reserved: HashSet::new(),
+            deferred: Vec::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash> Pending<K> {
+    fn release_deferred(&mut self) {
+        // taken first, as slots dropped from `Hash` or `Eq` may defer more keys
+        for key in std::mem::take(&mut self.deferred) {
+            self.reserved.remove(&key);
+        }
+    }
+}
+
+impl<K: Clone, V, S> FrozenMap<K, V, S> {
+    /// Collects clones of all the keys of this map into a vector.
+    ///
+    /// The returned keys are owned, so they are not tied to a borrow of the map.
+    /// The order of the keys is as if iterating a [`HashMap`] (stochastic).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// let mut keys = map.keys_cloned();
+    /// keys.sort();
+    ///
+    /// assert_eq!(keys, vec![1, 2]);
+    /// ```
+    pub fn keys_cloned(&self) -> Vec<K> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let keys = unsafe {
+            let map = self.map.get();
+            (*map).keys().cloned().collect()
+        };
+        self.in_use.set(false);
+        keys
     }
-
-    // TODO add more
 }
 
 impl<K: Eq + Hash + StableDeref, V: StableDeref, S: BuildHasher> FrozenMap<K, V, S> {
@@ -210,6 +1978,47 @@ impl<K: Eq + Hash + StableDeref, V: StableDeref, S: BuildHasher> FrozenMap<K, V,
         self.in_use.set(false);
         ret
     }
+
+    /// If the key exists in the map, returns references to the stored key and
+    /// its value, otherwise inserts `k` and `v` and returns references to them.
+    ///
+    /// The returned `bool` is `true` if the entry was newly inserted. Existing
+    /// entries are never overwritten; on a hit `k` and `v` are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// let (k, v, inserted) = map.get_or_insert_key_value(Box::new("1"), Box::new("a"));
+    /// assert_eq!((k, v, inserted), (&"1", &"a", true));
+    /// let (k2, v, inserted) = map.get_or_insert_key_value(Box::new("1"), Box::new("b"));
+    /// assert_eq!((v, inserted), (&"a", false));
+    /// assert!(std::ptr::eq(k, k2));
+    /// ```
+    pub fn get_or_insert_key_value(&self, k: K, v: V) -> (&K::Target, &V::Target, bool) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            match (*map).entry(k) {
+                hash_map::Entry::Occupied(entry) => {
+                    self.count(|stats| &stats.hits);
+                    let key: *const K::Target = &**entry.key();
+                    (&*key, &**entry.into_mut(), false)
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    self.count(|stats| &stats.misses);
+                    self.record_insert(entry.key());
+                    let key: *const K::Target = &**entry.key();
+                    (&*key, &**entry.insert(v), true)
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
 }
 
 impl<K, V, S> std::convert::AsMut<HashMap<K, V, S>> for FrozenMap<K, V, S> {
@@ -218,7 +2027,11 @@ impl<K, V, S> std::convert::AsMut<HashMap<K, V, S>> for FrozenMap<K, V, S> {
     /// This is safe, as it requires a `&mut self`, ensuring nothing is using
     /// the 'frozen' contents.
     fn as_mut(&mut self) -> &mut HashMap<K, V, S> {
-        unsafe { &mut *self.map.get() }
+        if let Some(order) = &mut self.order {
+            // keys may be inserted or removed behind its back
+            order.get_mut().stale = true;
+        }
+        self.map.get_mut()
     }
 }
 
@@ -227,6 +2040,10 @@ impl<K, V, S> From<HashMap<K, V, S>> for FrozenMap<K, V, S> {
         Self {
             map: UnsafeCell::new(map),
             in_use: Cell::new(false),
+            stats: None,
+            pending: UnsafeCell::new(None),
+            miss_hook: UnsafeCell::new(None),
+            order: None,
         }
     }
 }
@@ -267,10 +2084,7 @@ impl<K: Eq + Hash, V, S: BuildHasher + Default> FromIterator<(K, V)> for FrozenM
 
 impl<K: Eq + Hash, V, S: Default> Default for FrozenMap<K, V, S> {
     fn default() -> Self {
-        Self {
-            map: UnsafeCell::new(Default::default()),
-            in_use: Cell::new(false),
-        }
+        Self::from(HashMap::default())
     }
 }
 
@@ -281,6 +2095,13 @@ impl<K: Clone, V: Clone, S: Clone> Clone for FrozenMap<K, V, S> {
         let self_clone = Self {
             map: unsafe { self.map.get().as_ref().unwrap() }.clone().into(),
             in_use: Cell::from(false),
+            stats: self.stats.as_ref().map(|stats| Box::new(stats.duplicate())),
+            pending: UnsafeCell::new(None),
+            miss_hook: UnsafeCell::new(None),
+            order: self
+                .order
+                .as_ref()
+                .map(|order| Box::new(UnsafeCell::new(unsafe { (*order.get()).clone() }))),
         };
         self.in_use.set(false);
         return self_clone;
@@ -300,6 +2121,49 @@ impl<K: Eq + Hash, V: PartialEq + StableDeref> PartialEq for FrozenMap<K, V> {
     }
 }
 
+impl<K: Eq + Hash, V: Eq + StableDeref> Eq for FrozenMap<K, V> {}
+
+/// Maps are hashed without regard to the order of their entries, so that equal
+/// maps hash the same however they were built.
+///
+/// # Examples
+///
+/// ```
+/// use elsa::FrozenMap;
+/// use std::collections::HashSet;
+///
+/// let a = FrozenMap::new();
+/// a.insert(1, Box::new("a"));
+/// a.insert(2, Box::new("b"));
+/// let b = FrozenMap::new();
+/// b.insert(2, Box::new("b"));
+/// b.insert(1, Box::new("a"));
+///
+/// let mut seen = HashSet::new();
+/// assert!(seen.insert(a));
+/// assert!(!seen.insert(b));
+/// ```
+impl<K: Hash, V: Hash, S> Hash for FrozenMap<K, V, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        // combine the entry hashes with a commutative operation, using a
+        // fixed-key hasher so that equal entries agree across maps
+        let (len, combined) = unsafe {
+            let map = self.map.get();
+            let combined = (*map).iter().fold(0u64, |acc, entry| {
+                let mut hasher = DefaultHasher::new();
+                entry.hash(&mut hasher);
+                acc.wrapping_add(hasher.finish())
+            });
+            ((*map).len(), combined)
+        };
+        self.in_use.set(false);
+        state.write_usize(len);
+        state.write_u64(combined);
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<K, V, S> Serialize for FrozenMap<K, V, S>
 where
@@ -334,6 +2198,142 @@ where
     }
 }
 
+/// Lookup counts of a [`FrozenMap`], see [`FrozenMap::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups which found their key
+    pub hits: u64,
+    /// Lookups which did not find their key
+    pub misses: u64,
+    /// Entries inserted after a miss
+    pub inserts: u64,
+}
+
+/// The counters are atomic so that they do not need the `in_use` guard.
+#[derive(Debug, Default)]
+struct StatsCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    inserts: AtomicU64,
+}
+
+impl StatsCounters {
+    fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(atomic::Ordering::Relaxed),
+            misses: self.misses.load(atomic::Ordering::Relaxed),
+            inserts: self.inserts.load(atomic::Ordering::Relaxed),
+        }
+    }
+
+    fn duplicate(&self) -> Self {
+        let stats = self.snapshot();
+        StatsCounters {
+            hits: AtomicU64::new(stats.hits),
+            misses: AtomicU64::new(stats.misses),
+            inserts: AtomicU64::new(stats.inserts),
+        }
+    }
+}
+
+/// Insertion order of the keys of a map created with `with_indexing`
+#[derive(Debug, Clone)]
+struct Order<K> {
+    keys: Vec<K>,
+    /// Set when the map was mutably borrowed through `AsMut`, so `keys` must
+    /// be synced with it before use
+    stale: bool,
+    /// `K::clone`, captured where `K: Clone` is known
+    clone_key: fn(&K) -> K,
+}
+
+impl<K> Order<K> {
+    fn clear(&mut self) {
+        self.keys.clear();
+        self.stale = false;
+    }
+
+    fn push(&mut self, key: &K) {
+        self.keys.push((self.clone_key)(key));
+    }
+
+    fn retain<F: FnMut(&K) -> bool>(&mut self, f: F) {
+        self.keys.retain(f);
+    }
+}
+
+impl<K: Eq + Hash> Order<K> {
+    fn remove<Q: ?Sized>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Eq,
+    {
+        if let Some(index) = self.keys.iter().position(|k| k.borrow() == key) {
+            self.keys.remove(index);
+        }
+    }
+
+    /// Drops the keys no longer in `map` and appends those missing, if the
+    /// map was changed through `AsMut`.
+    fn sync<V, S: BuildHasher>(&mut self, map: &HashMap<K, V, S>) {
+        if !self.stale {
+            return;
+        }
+        self.stale = false;
+        self.keys.retain(|k| map.contains_key(k));
+        if self.keys.len() < map.len() {
+            let known: HashSet<&K> = self.keys.iter().collect();
+            let missing: Vec<K> = map
+                .keys()
+                .filter(|k| !known.contains(k))
+                .map(self.clone_key)
+                .collect();
+            self.keys.extend(missing);
+        }
+    }
+}
+
+struct MissHook<K>(Box<dyn Fn(&K) + Send>);
+
+/// The opt-in features of a map which record new values, borrowed from it
+/// so that entries can carry them
+struct Recorder<'a, K> {
+    stats: Option<&'a StatsCounters>,
+    order: Option<&'a UnsafeCell<Order<K>>>,
+    miss_hook: Option<&'a MissHook<K>>,
+}
+
+impl<K> Recorder<'_, K> {
+    /// Records a new key about to be inserted after a miss.
+    ///
+    /// Must only be called while the map is in use.
+    unsafe fn record_insert(&self, key: &K) {
+        self.record_reinsert(key);
+        if let Some(order) = self.order {
+            (*order.get()).push(key);
+        }
+    }
+
+    /// Records a new value about to replace a value which no longer counts,
+    /// for a key already in the map.
+    ///
+    /// Must only be called while the map is in use.
+    unsafe fn record_reinsert(&self, key: &K) {
+        if let Some(stats) = self.stats {
+            stats.inserts.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+        if let Some(hook) = self.miss_hook {
+            (hook.0)(key);
+        }
+    }
+}
+
+impl<K> fmt::Debug for MissHook<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MissHook")
+    }
+}
+
 /// Append-only version of `std::collections::BTreeMap` where
 /// insertion does not require mutable access
 #[derive(Debug)]
@@ -356,7 +2356,9 @@ impl<K: Clone + Ord, V: StableDeref> FrozenBTreeMap<K, V> {
             in_use: Cell::new(false),
         }
     }
+}
 
+impl<K, V> FrozenBTreeMap<K, V> {
     /// # Examples
     ///
     /// ```
@@ -393,6 +2395,72 @@ impl<K: Clone + Ord, V: StableDeref> FrozenBTreeMap<K, V> {
     }
 }
 
+impl<K: Ord, V> FrozenBTreeMap<K, V> {
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the
+    /// ordering on the borrowed form *must* match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let map = FrozenBTreeMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert!(map.contains_key(&1));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).contains_key(k)
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K: Clone + Ord + StableDeref, V: StableDeref> FrozenBTreeMap<K, V> {
+    /// Returns a reference to the key and value matching a borrowed
+    /// key.
+    ///
+    /// The key argument may be any borrowed form of the map's key type,
+    /// but the ordering on the borrowed form *must* match the ordering
+    /// on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let map = FrozenBTreeMap::new();
+    /// map.insert(Box::new("1"), Box::new("a"));
+    /// assert_eq!(map.get_key_value(&"1"), Some((&"1", &"a")));
+    /// assert_eq!(map.get_key_value(&"2"), None);
+    /// ```
+    pub fn get_key_value<Q: ?Sized>(&self, k: &Q) -> Option<(&K::Target, &V::Target)>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get_key_value(k).map(|(k, v)| (&**k, &**v))
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
 impl<K: Clone + Ord, V: StableDeref> FrozenBTreeMap<K, V> {
     // these should never return &K or &V
     // these should never delete any entries
@@ -460,14 +2528,186 @@ impl<K: Clone + Ord, V: StableDeref> FrozenBTreeMap<K, V> {
         Q: Ord,
         F: FnOnce(&V) -> T,
     {
-        assert!(!self.in_use.get());
-        self.in_use.set(true);
-        let ret = unsafe {
-            let map = self.map.get();
-            (*map).get(k).map(f)
-        };
-        self.in_use.set(false);
-        ret
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map).get(k).map(f)
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K: Clone + Ord, V: StableDeref> FrozenBTreeMap<K, V> {
+    /// Returns an iterator over the entries of the map, ordered by key.
+    ///
+    /// Keys are moved around in a [`BTreeMap`] on insertion, so like values
+    /// they have to be [`StableDeref`] to be handed out by reference.
+    ///
+    /// The map cannot be accessed while the iterator is alive; doing so
+    /// will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let map = FrozenBTreeMap::new();
+    /// map.insert(Box::new(2), Box::new("b"));
+    /// map.insert(Box::new(1), Box::new("a"));
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&1, &"a"), (&2, &"b")]);
+    /// assert_eq!(map.iter().rev().collect::<Vec<_>>(), vec![(&2, &"b"), (&1, &"a")]);
+    /// ```
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&K::Target, &V::Target)> + ExactSizeIterator
+    where
+        K: StableDeref,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let iter = unsafe { (*self.map.get()).iter() };
+        InUseIter {
+            iter: iter.map(|(k, v)| (&**k, &**v)),
+            in_use: &self.in_use,
+        }
+    }
+
+    /// Returns an iterator over the keys of the map, in order.
+    ///
+    /// The map cannot be accessed while the iterator is alive; doing so
+    /// will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let map = FrozenBTreeMap::new();
+    /// map.insert(Box::new(2), Box::new("b"));
+    /// map.insert(Box::new(1), Box::new("a"));
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), vec![&1, &2]);
+    /// ```
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K::Target> + ExactSizeIterator
+    where
+        K: StableDeref,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let keys = unsafe { (*self.map.get()).keys() };
+        InUseIter {
+            iter: keys.map(|k| &**k),
+            in_use: &self.in_use,
+        }
+    }
+
+    /// Returns an iterator over the values of the map, ordered by key.
+    ///
+    /// The map cannot be accessed while the iterator is alive; doing so
+    /// will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let map = FrozenBTreeMap::new();
+    /// map.insert(2, Box::new("b"));
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.values().collect::<Vec<_>>(), vec![&"a", &"b"]);
+    /// ```
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V::Target> + ExactSizeIterator {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let values = unsafe { (*self.map.get()).values() };
+        InUseIter {
+            iter: values.map(|v| &**v),
+            in_use: &self.in_use,
+        }
+    }
+
+    /// Splits the map in two at the given key, returning a new map with all
+    /// entries whose key is greater than or equal to `k`.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let mut map = FrozenBTreeMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// map.insert(2, Box::new("b"));
+    /// map.insert(3, Box::new("c"));
+    /// let newer = map.split_off(&2);
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(newer.get(&2), Some(&"b"));
+    /// assert_eq!(newer.get(&3), Some(&"c"));
+    /// ```
+    pub fn split_off<Q: ?Sized + Ord>(&mut self, k: &Q) -> Self
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get_mut().split_off(k).into()
+    }
+
+    /// Moves all entries of `other` into this map. Where both maps have a
+    /// value for a key, the one from `other` is kept.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let mut map = FrozenBTreeMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// let other = FrozenBTreeMap::new();
+    /// other.insert(1, Box::new("b"));
+    /// other.insert(2, Box::new("c"));
+    /// map.append(other);
+    /// assert_eq!(map.get(&1), Some(&"b"));
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn append(&mut self, other: Self) {
+        self.map.get_mut().append(&mut other.map.into_inner())
+    }
+
+    /// Returns an iterator over mutable references to the values of the
+    /// entries whose key is in `range`, ordered by key.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends, like [`BTreeMap::range_mut`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenBTreeMap;
+    ///
+    /// let mut map = FrozenBTreeMap::new();
+    /// map.insert(1, Box::new(1));
+    /// map.insert(2, Box::new(2));
+    /// map.insert(3, Box::new(3));
+    /// for (_, v) in map.range_mut(2..) {
+    ///     **v *= 10;
+    /// }
+    /// assert_eq!(map.get(&1), Some(&1));
+    /// assert_eq!(map.get(&3), Some(&30));
+    /// ```
+    pub fn range_mut<Q: ?Sized + Ord, R: RangeBounds<Q>>(&mut self, range: R) -> RangeMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get_mut().range_mut(range)
     }
 }
 
@@ -618,3 +2858,545 @@ where
         BTreeMap::deserialize(deserializer).map(FrozenBTreeMap::from)
     }
 }
+
+/// Size-bounded cache where insertion does not require mutable access,
+/// evicting the least recently used entry once it is full.
+///
+/// Eviction deletes entries, so unlike the other frozen maps this cannot hand
+/// out plain references into its values: they would dangle once the entry is
+/// evicted. Values are instead stored as [`Arc`]s, and lookups return clones
+/// of them, which keep an evicted value alive for as long as they are held.
+#[derive(Debug)]
+pub struct FrozenLruMap<K, V, S = RandomState> {
+    lru: UnsafeCell<Lru<K, V, S>>,
+    /// Eq/Hash implementations can have side-effects, and using Rc it is possible
+    /// for FrozenLruMap::get_or_insert to be called on a key that itself contains the same
+    /// `FrozenLruMap`, whose `eq` implementation also calls FrozenLruMap::get_or_insert
+    ///
+    /// We use this `in_use` flag to guard against any reentrancy.
+    in_use: Cell<bool>,
+}
+
+struct Lru<K, V, S> {
+    /// The values, with the tick at which they were last used
+    entries: HashMap<K, (Arc<V>, u64), S>,
+    /// The keys, ordered by the tick at which they were last used
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+}
+
+// safety: UnsafeCell implies !Sync
+
+impl<K: Eq + Hash + Clone, V> FrozenLruMap<K, V> {
+    /// Creates an empty cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K, V, S> FrozenLruMap<K, V, S> {
+    /// Creates an empty cache holding at most `capacity` entries, which will
+    /// use the given hasher.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            lru: UnsafeCell::new(Lru {
+                entries: HashMap::with_hasher(hasher),
+                order: BTreeMap::new(),
+                tick: 0,
+                capacity,
+            }),
+            in_use: Cell::new(false),
+        }
+    }
+
+    /// Returns the maximum number of entries the cache holds.
+    pub fn capacity(&self) -> usize {
+        assert!(!self.in_use.get());
+        unsafe { (*self.lru.get()).capacity }
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenLruMap;
+    ///
+    /// let map = FrozenLruMap::new(1);
+    /// assert_eq!(map.len(), 0);
+    /// map.get_or_insert(1, "a");
+    /// map.get_or_insert(2, "b");
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let len = unsafe {
+            let lru = self.lru.get();
+            (*lru).entries.len()
+        };
+        self.in_use.set(false);
+        len
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenLruMap;
+    ///
+    /// let map = FrozenLruMap::new(1);
+    /// assert_eq!(map.is_empty(), true);
+    /// map.get_or_insert(1, "a");
+    /// assert_eq!(map.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Eq + Hash + Clone, V, S: BuildHasher> FrozenLruMap<K, V, S> {
+    /// Returns the value corresponding to the key, marking it as the most
+    /// recently used entry.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenLruMap;
+    ///
+    /// let map = FrozenLruMap::new(2);
+    /// map.get_or_insert(1, "a");
+    /// assert_eq!(map.get(&1).as_deref(), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let lru = &mut *self.lru.get();
+            lru.touch(k)
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// If the key exists in the map, returns its value, otherwise inserts `v`
+    /// for the key and returns it, evicting the least recently used entry if
+    /// the map is over capacity. Either way, the entry becomes the most
+    /// recently used one.
+    ///
+    /// Existing values are never overwritten.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenLruMap;
+    /// use std::sync::Arc;
+    ///
+    /// let map = FrozenLruMap::new(2);
+    /// let a = map.get_or_insert(1, "a");
+    /// map.get_or_insert(2, "b");
+    /// map.get(&1);
+    /// // 2 is now the least recently used entry
+    /// map.get_or_insert(3, "c");
+    /// assert_eq!(map.get(&2), None);
+    /// assert!(Arc::ptr_eq(&a, &map.get_or_insert(1, "x")));
+    /// ```
+    pub fn get_or_insert(&self, k: K, v: V) -> Arc<V> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let lru = &mut *self.lru.get();
+            match lru.touch(&k) {
+                Some(existing) => existing,
+                None => lru.insert(k, v),
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K: Eq + Hash + Clone, V, S: BuildHasher> Lru<K, V, S> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn touch<Q: ?Sized>(&mut self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let tick = self.next_tick();
+        let (value, last_used) = self.entries.get_mut(k)?;
+        let key = self.order.remove(last_used).unwrap();
+        self.order.insert(tick, key);
+        *last_used = tick;
+        Some(Arc::clone(value))
+    }
+
+    fn insert(&mut self, k: K, v: V) -> Arc<V> {
+        let tick = self.next_tick();
+        let value = Arc::new(v);
+        self.order.insert(tick, k.clone());
+        self.entries.insert(k, (Arc::clone(&value), tick));
+        while self.entries.len() > self.capacity {
+            let (_, oldest) = self.order.pop_first().unwrap();
+            self.entries.remove(&oldest);
+        }
+        value
+    }
+}
+
+/// Cache where insertion does not require mutable access, and entries go
+/// stale once they are older than a maximum age given at lookup.
+///
+/// Stale entries are treated as absent, and replaced when inserting for
+/// their key again. As this deletes the old values, they are stored as
+/// [`Arc`]s, like in [`FrozenLruMap`], and lookups return clones of them.
+/// Stale entries are only dropped by [`FrozenTtlMap::purge_expired`] or by
+/// being replaced.
+#[derive(Debug)]
+pub struct FrozenTtlMap<K, V, S = RandomState> {
+    /// The values, with the instant they were inserted at
+    map: UnsafeCell<HashMap<K, (Arc<V>, Instant), S>>,
+    /// Eq/Hash implementations can have side-effects, and using Rc it is possible
+    /// for FrozenTtlMap::get_or_insert to be called on a key that itself contains the same
+    /// `FrozenTtlMap`, whose `eq` implementation also calls FrozenTtlMap::get_or_insert
+    ///
+    /// We use this `in_use` flag to guard against any reentrancy.
+    in_use: Cell<bool>,
+}
+
+// safety: UnsafeCell implies !Sync
+
+impl<K: Eq + Hash, V> FrozenTtlMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, S> FrozenTtlMap<K, V, S> {
+    /// Creates an empty map which will use the given hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            map: UnsafeCell::new(HashMap::with_hasher(hasher)),
+            in_use: Cell::new(false),
+        }
+    }
+
+    /// Returns the number of entries in the map, including stale ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenTtlMap;
+    /// use std::time::Duration;
+    ///
+    /// let map = FrozenTtlMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.get_or_insert(1, "a", Duration::from_secs(60));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let len = unsafe {
+            let map = self.map.get();
+            (*map).len()
+        };
+        self.in_use.set(false);
+        len
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenTtlMap;
+    /// use std::time::Duration;
+    ///
+    /// let map = FrozenTtlMap::new();
+    /// assert_eq!(map.is_empty(), true);
+    /// map.get_or_insert(1, "a", Duration::from_secs(60));
+    /// assert_eq!(map.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all entries older than `max_age`.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents. Values still held elsewhere stay alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenTtlMap;
+    /// use std::time::Duration;
+    ///
+    /// let mut map = FrozenTtlMap::new();
+    /// let a = map.get_or_insert(1, "a", Duration::from_secs(60));
+    /// std::thread::sleep(Duration::from_millis(1));
+    /// map.purge_expired(Duration::ZERO);
+    /// assert!(map.is_empty());
+    /// assert_eq!(*a, "a");
+    /// ```
+    pub fn purge_expired(&mut self, max_age: Duration) {
+        self.map
+            .get_mut()
+            .retain(|_, (_, inserted)| inserted.elapsed() <= max_age)
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenTtlMap<K, V, S> {
+    /// Returns the value corresponding to the key, unless it is older than
+    /// `max_age`.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenTtlMap;
+    /// use std::time::Duration;
+    ///
+    /// let map = FrozenTtlMap::new();
+    /// map.get_or_insert(1, "a", Duration::from_secs(60));
+    /// assert_eq!(map.get_fresh(&1, Duration::from_secs(60)).as_deref(), Some(&"a"));
+    /// std::thread::sleep(Duration::from_millis(1));
+    /// assert_eq!(map.get_fresh(&1, Duration::ZERO), None);
+    /// ```
+    pub fn get_fresh<Q: ?Sized>(&self, k: &Q, max_age: Duration) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            (*map)
+                .get(k)
+                .filter(|(_, inserted)| inserted.elapsed() <= max_age)
+                .map(|(value, _)| Arc::clone(value))
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// If the key has a value no older than `max_age`, returns it, otherwise
+    /// inserts `v` for the key and returns it, replacing any stale value.
+    ///
+    /// Fresh values are never overwritten.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenTtlMap;
+    /// use std::time::Duration;
+    ///
+    /// let map = FrozenTtlMap::new();
+    /// let minute = Duration::from_secs(60);
+    /// assert_eq!(*map.get_or_insert(1, "a", minute), "a");
+    /// assert_eq!(*map.get_or_insert(1, "b", minute), "a");
+    /// std::thread::sleep(Duration::from_millis(1));
+    /// let stale = Duration::ZERO;
+    /// assert_eq!(*map.get_or_insert(1, "c", stale), "c");
+    /// ```
+    pub fn get_or_insert(&self, k: K, v: V, max_age: Duration) -> Arc<V> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let map = self.map.get();
+            match (*map).entry(k) {
+                hash_map::Entry::Occupied(mut entry) => {
+                    if entry.get().1.elapsed() > max_age {
+                        entry.insert((Arc::new(v), Instant::now()));
+                    }
+                    Arc::clone(&entry.get().0)
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    Arc::clone(&entry.insert((Arc::new(v), Instant::now())).0)
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<K: Eq + Hash, V, S: Default> Default for FrozenTtlMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+/// Wraps an iterator over the contents of a frozen map, keeping the map
+/// marked as in use until the iterator is dropped.
+struct InUseIter<'a, I> {
+    iter: I,
+    in_use: &'a Cell<bool>,
+}
+
+impl<'a, I: Iterator> Iterator for InUseIter<'a, I> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<I::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, I: DoubleEndedIterator> DoubleEndedIterator for InUseIter<'a, I> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl<'a, I: ExactSizeIterator> ExactSizeIterator for InUseIter<'a, I> {}
+
+impl<'a, I> Drop for InUseIter<'a, I> {
+    fn drop(&mut self) {
+        self.in_use.set(false);
+    }
+}
+
+#[test]
+fn test_opt_in_size() {
+    use std::mem::size_of;
+
+    // the opt-in features only cost an empty pointer each, next to `in_use`
+    assert_eq!(
+        size_of::<FrozenMap<u32, Box<u32>>>(),
+        size_of::<HashMap<u32, Box<u32>>>() + 5 * size_of::<usize>()
+    );
+}
+
+#[test]
+fn test_stats_and_miss_hook() {
+    use std::sync::atomic::AtomicUsize;
+
+    fn with_hook<V>(map: &FrozenMap<String, V>) -> Arc<AtomicUsize> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        map.on_miss(move |_| {
+            counter.fetch_add(1, atomic::Ordering::Relaxed);
+        });
+        calls
+    }
+
+    let map: FrozenMap<String, Box<str>> = FrozenMap::with_stats();
+    let calls = with_hook(&map);
+    for _ in 0..2 {
+        map.entry("a".into()).or_insert_with(|| "a".into());
+        map.entry_ref("b").or_insert("b".into());
+        map.get_or_insert_default_then("c".into(), |_| ());
+        map.get_or_insert_with_key_ref("d", || "d".into());
+        map.get_or_insert_key_value("e".into(), "e".into());
+        map.get_or_insert_recursive("f".into(), |_| "f".into())
+            .unwrap();
+    }
+    map.reserve_slot("g".into()).unwrap().fill("g".into());
+    let stats = map.stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.inserts), (6, 6, 7));
+    assert_eq!(calls.load(atomic::Ordering::Relaxed), 7);
+
+    let map: FrozenMap<String, Weak<String>> = FrozenMap::with_stats();
+    let calls = with_hook(&map);
+    let a = map.get_or_insert_weak("a".into(), || Arc::new("a".into()));
+    map.get_or_insert_weak("a".into(), || unreachable!());
+    drop(a);
+    map.get_or_insert_weak("a".into(), || Arc::new("b".into()));
+    let stats = map.stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.inserts), (1, 2, 2));
+    assert_eq!(calls.load(atomic::Ordering::Relaxed), 2);
+}
+
+// The tests below exercise the unsafe paths of the map, and are meant to also
+// be run under Miri, see the README.
+
+#[test]
+fn test_references_survive_rehash() {
+    let map = FrozenMap::new();
+    let values: Vec<&str> = (0..32).map(|i| map.insert(i, i.to_string())).collect();
+    // grows the table, moving the entries but not the strings they point to
+    map.reserve(1000);
+    map.insert(100, "100".to_string());
+    map.shrink_to_fit();
+    for (i, value) in values.into_iter().enumerate() {
+        assert_eq!(value, i.to_string());
+    }
+}
+
+#[test]
+fn test_reentrant_hash_panics() {
+    use std::ptr;
+
+    thread_local! {
+        /// The map the keys below look up other keys in
+        static MAP: Cell<*const FrozenMap<Key, Box<u32>>> = Cell::new(ptr::null());
+    }
+
+    /// A key whose `Hash` looks another key up in `MAP` if `reenter` is set
+    struct Key {
+        id: u32,
+        reenter: bool,
+    }
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl Hash for Key {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            if self.reenter {
+                let map = unsafe { &*MAP.with(|map| map.get()) };
+                map.get(&Key {
+                    id: 0,
+                    reenter: false,
+                });
+            }
+            self.id.hash(state);
+        }
+    }
+
+    let map = FrozenMap::new();
+    MAP.with(|cell| cell.set(&map));
+    let zero = map.insert(
+        Key {
+            id: 0,
+            reenter: false,
+        },
+        Box::new(0),
+    );
+    // reading from `Hash` while inserting would alias the table being
+    // modified, so it panics instead
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        map.insert(
+            Key {
+                id: 1,
+                reenter: true,
+            },
+            Box::new(1),
+        );
+    }));
+    assert!(result.is_err());
+    assert_eq!(zero, &0);
+    MAP.with(|cell| cell.set(ptr::null()));
+}
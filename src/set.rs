@@ -0,0 +1,359 @@
+use std::borrow::Borrow;
+use std::cell::{Cell, UnsafeCell};
+use std::collections::hash_map::RandomState;
+use std::collections::hash_set;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
+
+use stable_deref_trait::StableDeref;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Append-only version of `std::collections::HashSet` where
+/// insertion does not require mutable access
+#[derive(Debug)]
+pub struct FrozenSet<T, S = RandomState> {
+    set: UnsafeCell<HashSet<T, S>>,
+    /// Eq/Hash implementations can have side-effects, and using Rc it is possible
+    /// for FrozenSet::insert to be called on a key that itself contains the same
+    /// `FrozenSet`, whose `eq` implementation also calls FrozenSet::insert
+    ///
+    /// We use this `in_use` flag to guard against any reentrancy.
+    in_use: Cell<bool>,
+}
+
+// safety: UnsafeCell implies !Sync
+
+impl<T: Eq + Hash> FrozenSet<T> {
+    pub fn new() -> Self {
+        Self::from(HashSet::new())
+    }
+}
+
+impl<T: Eq + Hash + StableDeref, S: BuildHasher> FrozenSet<T, S> {
+    // these should never return &T
+    // these should never delete any entries
+    //
+    /// If the value exists in the set, returns a reference to the corresponding
+    /// value, otherwise inserts a new entry in the set for that value
+    /// and returns a reference to it.
+    ///
+    /// Existing values are never overwritten.
+    ///
+    /// # Example
+    /// ```
+    /// use elsa::FrozenSet;
+    /// let set = FrozenSet::new();
+    /// let a_ref = set.insert(Box::new("a"));
+    /// assert!(std::ptr::eq(a_ref, set.insert(Box::new("a"))));
+    /// ```
+    pub fn insert(&self, value: T) -> &T::Target {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = self.set.get();
+            match (*set).get(&value) {
+                Some(existing) => &**existing,
+                None => {
+                    // StableDeref guarantees the target stays put when `value` is moved
+                    let ret: *const T::Target = &*value;
+                    (*set).insert(value);
+                    &*ret
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Returns a reference to the value passed as argument if present in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    ///
+    /// let set = FrozenSet::new();
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.get(&Box::new("a")), Some(&"a"));
+    /// assert_eq!(set.get(&Box::new("b")), None);
+    /// ```
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&T::Target>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = self.set.get();
+            (*set).get(k).map(|x| &**x)
+        };
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Returns an iterator over the values of the set, in arbitrary order.
+    ///
+    /// The set cannot be accessed while the iterator is alive; doing so
+    /// will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    ///
+    /// let set = FrozenSet::new();
+    /// set.insert(Box::new("a"));
+    /// set.insert(Box::new("b"));
+    /// let mut values = set.iter().collect::<Vec<_>>();
+    /// values.sort();
+    /// assert_eq!(values, vec![&"a", &"b"]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        Iter {
+            iter: unsafe { (*self.set.get()).iter() },
+            in_use: &self.in_use,
+        }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> FrozenSet<T, S> {
+    /// Returns `true` if the set contains the value passed as argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    ///
+    /// let set = FrozenSet::new();
+    /// set.insert(Box::new("a"));
+    /// assert!(set.contains(&Box::new("a")));
+    /// assert!(!set.contains(&Box::new("b")));
+    /// ```
+    pub fn contains<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe {
+            let set = self.set.get();
+            (*set).contains(k)
+        };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<T, S> FrozenSet<T, S> {
+    /// Creates an empty set which will use the given hasher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let set = FrozenSet::with_hasher(RandomState::new());
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::from(HashSet::with_hasher(hasher))
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    ///
+    /// let set = FrozenSet::new();
+    /// assert_eq!(set.len(), 0);
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let len = unsafe {
+            let set = self.set.get();
+            (*set).len()
+        };
+        self.in_use.set(false);
+        len
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    ///
+    /// let set = FrozenSet::new();
+    /// assert_eq!(set.is_empty(), true);
+    /// set.insert(Box::new("a"));
+    /// assert_eq!(set.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_set(self) -> HashSet<T, S> {
+        self.set.into_inner()
+    }
+
+    /// Freezes a standard [`HashSet`]. This is the same as the [`From`] conversion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenSet;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut std = HashSet::new();
+    /// std.insert(Box::new("a"));
+    /// let frozen = FrozenSet::from_std(std);
+    /// assert_eq!(frozen.get(&Box::new("a")), Some(&"a"));
+    /// let std: HashSet<_, _> = frozen.into_std();
+    /// assert_eq!(std.len(), 1);
+    /// ```
+    pub fn from_std(set: HashSet<T, S>) -> Self {
+        Self::from(set)
+    }
+
+    /// Unfreezes into the standard [`HashSet`].
+    pub fn into_std(self) -> HashSet<T, S> {
+        self.into_set()
+    }
+
+    /// Get mutable access to the underlying [`HashSet`].
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn as_mut(&mut self) -> &mut HashSet<T, S> {
+        unsafe { &mut *self.set.get() }
+    }
+
+    // TODO add more
+}
+
+/// Iterator over FrozenSet, obtained via `.iter()`
+///
+/// The set is marked as in use for as long as the iterator is alive
+pub struct Iter<'a, T> {
+    iter: hash_set::Iter<'a, T>,
+    in_use: &'a Cell<bool>,
+}
+
+impl<'a, T: StableDeref> Iterator for Iter<'a, T> {
+    type Item = &'a T::Target;
+    fn next(&mut self) -> Option<&'a T::Target> {
+        self.iter.next().map(|x| &**x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T> Drop for Iter<'a, T> {
+    fn drop(&mut self) {
+        self.in_use.set(false);
+    }
+}
+
+impl<'a, T: Eq + Hash + StableDeref, S: BuildHasher> IntoIterator for &'a FrozenSet<T, S> {
+    type Item = &'a T::Target;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T, S> From<HashSet<T, S>> for FrozenSet<T, S> {
+    fn from(set: HashSet<T, S>) -> Self {
+        Self {
+            set: UnsafeCell::new(set),
+            in_use: Cell::new(false),
+        }
+    }
+}
+
+impl<T: Eq + Hash, S: Default + BuildHasher> FromIterator<T> for FrozenSet<T, S> {
+    fn from_iter<U>(iter: U) -> Self
+    where
+        U: IntoIterator<Item = T>,
+    {
+        let set: HashSet<_, _> = iter.into_iter().collect();
+        set.into()
+    }
+}
+
+impl<T: Eq + Hash, S: Default> Default for FrozenSet<T, S> {
+    fn default() -> Self {
+        Self::from(HashSet::default())
+    }
+}
+
+impl<T: Clone, S: Clone> Clone for FrozenSet<T, S> {
+    fn clone(&self) -> Self {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let self_clone = Self {
+            set: unsafe { self.set.get().as_ref().unwrap() }.clone().into(),
+            in_use: Cell::from(false),
+        };
+        self.in_use.set(false);
+        self_clone
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> PartialEq for FrozenSet<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        assert!(!self.in_use.get());
+        assert!(!other.in_use.get());
+        self.in_use.set(true);
+        other.in_use.set(true);
+        let ret = unsafe { self.set.get().as_ref() == other.set.get().as_ref() };
+        self.in_use.set(false);
+        other.in_use.set(false);
+        ret
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, S> Serialize for FrozenSet<T, S>
+where
+    T: Eq + Hash + Serialize,
+    S: BuildHasher,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let set_serialized = unsafe { self.set.get().as_ref().unwrap() }.serialize(serializer);
+        self.in_use.set(false);
+        set_serialized
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, S> Deserialize<'de> for FrozenSet<K, S>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        HashSet::deserialize(deserializer).map(FrozenSet::from)
+    }
+}
@@ -25,18 +25,26 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicPtr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::PoisonError;
 use std::sync::RwLock;
 use std::sync::TryLockError;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+#[cfg(feature = "dashmap")]
+pub mod concurrent;
+
 /// Append-only threadsafe version of `std::collections::HashMap` where
 /// insertion does not require mutable access
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
-    serde(bound(deserialize = "K: Eq + Hash + Deserialize<'de>, V: Deserialize<'de>"))
+    serde(bound(
+        serialize = "K: Serialize, V: Serialize",
+        deserialize = "K: Eq + Hash + Deserialize<'de>, V: Deserialize<'de>, S: BuildHasher + Default"
+    ))
 )]
 pub struct FrozenMap<K, V, S = RandomState> {
     map: RwLock<HashMap<K, V, S>>,
@@ -237,6 +245,76 @@ impl<K: Eq + Hash, V: StableDeref, S: BuildHasher> FrozenMap<K, V, S> {
     }
 }
 
+#[cfg(feature = "rayon")]
+impl<K, V: StableDeref, S> FrozenMap<K, V, S>
+where
+    V::Target: Sync,
+{
+    /// Returns a parallel iterator over the values of the map, in arbitrary order.
+    ///
+    /// The references to the values are first collected into a vector, which
+    /// costs an allocation of one pointer per value, with the read lock held.
+    /// `HashMap` cannot be split for parallel work, so rayon's own iterator
+    /// over a map collects the same way. Since the values are never moved or
+    /// removed, the iteration itself then runs without the lock, so the
+    /// parallel tasks may insert into the map without deadlocking; such
+    /// insertions are not observed by the iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::FrozenMap;
+    /// use rayon::prelude::*;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new(1));
+    /// map.insert(2, Box::new(2));
+    /// assert_eq!(map.par_values().sum::<i32>(), 3);
+    /// ```
+    pub fn par_values(&self) -> rayon::vec::IntoIter<&V::Target> {
+        use rayon::iter::IntoParallelIterator;
+
+        let map = self.map.read().unwrap();
+        let values: Vec<&V::Target> = map
+            .values()
+            .map(|x| unsafe { &*(&**x as *const V::Target) })
+            .collect();
+        values.into_par_iter()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> FrozenMap<K, Arc<V>, S> {
+    /// Returns a clone of the [`Arc`] corresponding to the key.
+    ///
+    /// This only bumps the reference count, unlike cloning the value
+    /// behind it, and the returned `Arc` can be moved to other threads
+    /// independently of the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::FrozenMap;
+    /// use std::sync::Arc;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Arc::new("a"));
+    /// let a = map.get_arc(&1).unwrap();
+    /// assert_eq!(Arc::strong_count(&a), 2);
+    /// assert_eq!(map.get_arc(&2), None);
+    /// ```
+    pub fn get_arc<Q: ?Sized>(&self, k: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map_get(k, Arc::clone)
+    }
+}
+
 impl<K, V, S> FrozenMap<K, V, S> {
     /// Collects the contents of this map into a vector of tuples.
     ///
@@ -263,6 +341,28 @@ impl<K, V, S> FrozenMap<K, V, S> {
             .collect::<Vec<_>>()
     }
 
+    /// Consumes the map, returning the underlying [`HashMap`].
+    ///
+    /// Unlike the other methods, this does not panic if the lock was poisoned
+    /// by a thread panicking while holding it: the map is recovered as it was
+    /// left. Entries may be missing if the panic came from a `Hash` or `Eq`
+    /// implementation, which the underlying `HashMap` does not guard against.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::FrozenMap;
+    ///
+    /// let map = FrozenMap::new();
+    /// map.insert(1, Box::new("a"));
+    /// assert_eq!(map.into_inner().get(&1), Some(&Box::new("a")));
+    /// ```
+    pub fn into_inner(self) -> HashMap<K, V, S> {
+        self.map
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// # Examples
     ///
     /// ```
@@ -454,6 +554,48 @@ fn test_sync_frozen_map() {
 
         let map_serde = serde_json::from_str::<FrozenMap<String, String>>(&map_json).unwrap();
         assert_eq!(map, map_serde);
+
+        // unknown fields are skipped and the sequence form is accepted
+        let map_other =
+            serde_json::from_str::<FrozenMap<String, String>>("{\"x\":[1],\"map\":{\"a\":\"b\"}}")
+                .unwrap();
+        assert_eq!(map, map_other);
+        let map_seq = serde_json::from_str::<FrozenMap<String, String>>("[{\"a\":\"b\"}]").unwrap();
+        assert_eq!(map, map_seq);
+        assert!(serde_json::from_str::<FrozenMap<String, String>>("{}").is_err());
+    }
+}
+
+#[test]
+fn test_sync_frozen_map_into_inner_poisoned() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let map = FrozenMap::new();
+    map.insert(1, Box::new("a"));
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let _guard = map.map.write().unwrap();
+        panic!("poison the lock");
+    }));
+    assert!(map.map.is_poisoned());
+    assert_eq!(map.into_inner().get(&1), Some(&Box::new("a")));
+}
+
+#[test]
+fn test_sync_frozen_map_custom_hasher() {
+    #[cfg(feature = "serde")]
+    {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        type Hasher = BuildHasherDefault<DefaultHasher>;
+
+        let map: FrozenMap<String, String, Hasher> = FrozenMap::default();
+        map.insert(String::from("a"), String::from("b"));
+
+        let map_json = serde_json::to_string(&map).unwrap();
+        let map_serde =
+            serde_json::from_str::<FrozenMap<String, String, Hasher>>(&map_json).unwrap();
+        assert_eq!(map, map_serde);
     }
 }
 
@@ -519,10 +661,15 @@ impl<T: StableDeref> FrozenVec<T> {
         unsafe { &*(&**vec.get_unchecked(vec.len() - 1) as *const T::Target) }
     }
 
-    /// Push, immediately getting a an index of the element
+    /// Push, immediately getting an index of the element
     ///
     /// Index can then be used with the `get` method
     ///
+    /// The push and the index computation happen under the same lock, so the
+    /// returned index is correct even when several threads push concurrently
+    /// (unlike calling `push` and then `len`). Since the vector is append-only,
+    /// the index stays valid forever.
+    ///
     /// # Examples
     ///
     /// ```
@@ -650,6 +797,26 @@ impl<T: PartialEq> PartialEq for FrozenVec<T> {
     }
 }
 
+#[test]
+fn test_push_get_index_concurrent() {
+    let vec = FrozenVec::new();
+
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let vec = &vec;
+            s.spawn(move || {
+                for i in 0..100 {
+                    let value = format!("{}-{}", t, i);
+                    let idx = vec.push_get_index(value.clone());
+                    assert_eq!(vec.get(idx), Some(&*value));
+                }
+            });
+        }
+    });
+
+    assert_eq!(vec.len(), 400);
+}
+
 #[test]
 fn test_sync_frozen_vec() {
     #[cfg(feature = "serde")]
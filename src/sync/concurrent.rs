@@ -0,0 +1,159 @@
+//! Threadsafe frozen collections backed by sharded concurrent maps.
+//!
+//! Unlike [`super::FrozenMap`], which puts the whole map behind a single
+//! `RwLock`, these only lock the shard an operation touches, so writes to
+//! different keys do not serialize.
+
+use dashmap::DashMap;
+use stable_deref_trait::StableDeref;
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// Append-only threadsafe version of `std::collections::HashMap`, backed by a
+/// [`DashMap`], where insertion does not require mutable access
+///
+/// Entries are never removed or overwritten through a shared reference, and
+/// values are [`StableDeref`], so references to them stay valid after the shard
+/// lock is released, even if the shard is resized.
+pub struct FrozenDashMap<K, V, S = RandomState> {
+    map: DashMap<K, V, S>,
+}
+
+impl<K: Eq + Hash + fmt::Debug, V: fmt::Debug, S: BuildHasher + Clone> fmt::Debug
+    for FrozenDashMap<K, V, S>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FrozenDashMap").field(&self.map).finish()
+    }
+}
+
+impl<K: Eq + Hash, V, S: Default + BuildHasher + Clone> Default for FrozenDashMap<K, V, S> {
+    fn default() -> Self {
+        Self {
+            map: Default::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> FrozenDashMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> FrozenDashMap<K, V, S> {
+    /// Creates an empty map which will use the given hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            map: DashMap::with_hasher(hasher),
+        }
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::concurrent::FrozenDashMap;
+    ///
+    /// let map = FrozenDashMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.get_or_insert(1, Box::new("a"));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::concurrent::FrozenDashMap;
+    ///
+    /// let map = FrozenDashMap::new();
+    /// assert_eq!(map.is_empty(), true);
+    /// map.get_or_insert(1, Box::new("a"));
+    /// assert_eq!(map.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K: Eq + Hash, V: StableDeref, S: BuildHasher + Clone> FrozenDashMap<K, V, S> {
+    // these should never return &K or &V
+    // these should never delete any entries
+
+    /// If the key exists in the map, returns a reference
+    /// to the corresponding value, otherwise inserts a
+    /// new entry in the map for that key and returns a
+    /// reference to the given value.
+    ///
+    /// Existing values are never overwritten.
+    ///
+    /// Only the shard holding the key is locked, and only for the duration of
+    /// this call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::concurrent::FrozenDashMap;
+    ///
+    /// let map = FrozenDashMap::new();
+    /// assert_eq!(map.get_or_insert(1, Box::new("a")), &"a");
+    /// assert_eq!(map.get_or_insert(1, Box::new("b")), &"a");
+    /// ```
+    pub fn get_or_insert(&self, k: K, v: V) -> &V::Target {
+        let inserted = self.map.entry(k).or_insert(v);
+        unsafe { &*(&**inserted as *const V::Target) }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::sync::concurrent::FrozenDashMap;
+    ///
+    /// let map = FrozenDashMap::new();
+    /// map.get_or_insert(1, Box::new("a"));
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V::Target>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let value = self.map.get(k)?;
+        Some(unsafe { &*(&**value as *const V::Target) })
+    }
+}
+
+#[test]
+fn test_frozen_dash_map_concurrent() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let map = Arc::new(FrozenDashMap::new());
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let map = Arc::clone(&map);
+            thread::spawn(move || {
+                for i in 0..100 {
+                    let v = map.get_or_insert(i, Box::new(i * 10 + t));
+                    assert_eq!(v / 10, i);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(map.len(), 100);
+    assert_eq!(map.get(&5).map(|v| v / 10), Some(5));
+}
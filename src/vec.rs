@@ -1,7 +1,10 @@
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
 use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, TryReserveError};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::iter::FromIterator;
-use std::ops::Index;
+use std::ops::{Bound, Deref, Index, Range, RangeBounds};
 
 use stable_deref_trait::StableDeref;
 
@@ -10,8 +13,14 @@ use stable_deref_trait::StableDeref;
 #[derive(Debug)]
 pub struct FrozenVec<T> {
     vec: UnsafeCell<Vec<T>>,
-    // XXXManishearth do we need a reentrancy guard here as well?
-    // StableDeref may not guarantee that there are no side effects
+    /// Methods like `for_range` hand out slices of the vector to user code,
+    /// which could try to push to it and reallocate the slice away.
+    ///
+    /// We use this `in_use` flag to guard against any reentrancy.
+    in_use: Cell<bool>,
+    /// Opt-in features, only allocated by the constructors enabling them,
+    /// so that other vectors just carry an empty pointer
+    config: Option<Box<Config>>,
 }
 
 // safety: UnsafeCell implies !Sync
@@ -21,8 +30,56 @@ impl<T> FrozenVec<T> {
     pub fn new() -> Self {
         Self {
             vec: UnsafeCell::new(Default::default()),
+            in_use: Cell::new(false),
+            config: None,
+        }
+    }
+
+    /// Constructs a new, empty vector which also keeps a hash index of its
+    /// elements, for use with [`FrozenVec::push_dedup`].
+    ///
+    /// The index costs a hash and an index, 16 bytes on 64-bit targets, per
+    /// element, on top of the hash table's own overhead. It lives in a
+    /// separate allocation, so vectors created otherwise only pay for an empty
+    /// pointer. It is rebuilt lazily after any change made through `&mut self`.
+    pub fn with_dedup() -> Self {
+        Self {
+            config: Some(Box::new(Config {
+                dedup: Some(Default::default()),
+                ..Config::default()
+            })),
+            ..Self::new()
         }
     }
+
+    /// Constructs a new, empty vector which [`FrozenVec::try_push`] will not
+    /// grow beyond `max_len` elements.
+    ///
+    /// Other methods, such as [`FrozenVec::push`], ignore the limit. Like the
+    /// index of [`FrozenVec::with_dedup`], the limit is stored in a separate
+    /// allocation.
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            config: Some(Box::new(Config {
+                max_len: Some(max_len),
+                ..Config::default()
+            })),
+            ..Self::new()
+        }
+    }
+
+    /// Mutable access to the vector, dropping the dedup index as elements may
+    /// be moved or changed.
+    fn vec_mut(&mut self) -> &mut Vec<T> {
+        if let Some(dedup) = self
+            .config
+            .as_mut()
+            .and_then(|config| config.dedup.as_mut())
+        {
+            dedup.get_mut().clear();
+        }
+        self.vec.get_mut()
+    }
 }
 
 impl<T> FrozenVec<T> {
@@ -31,28 +88,211 @@ impl<T> FrozenVec<T> {
 
     /// Appends an element to the back of the vector.
     pub fn push(&self, val: T) {
+        assert!(!self.in_use.get());
         unsafe {
             let vec = self.vec.get();
             (*vec).push(val)
         }
     }
+
+    /// Appends all the elements of `items` to the back of the vector, reserving
+    /// room for `items.len()` of them up front.
+    ///
+    /// The vector cannot be pushed to by `items` while it is iterated; doing
+    /// so will panic.
+    pub fn push_batch<I: ExactSizeIterator<Item = T>>(&self, items: I) {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        unsafe {
+            let vec = self.vec.get();
+            (*vec).reserve(items.len());
+            for item in items {
+                (*vec).push(item);
+            }
+        }
+        self.in_use.set(false);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements,
+    /// returning an error instead of aborting if the allocation fails.
+    pub fn try_reserve(&self, additional: usize) -> Result<(), TryReserveError> {
+        assert!(!self.in_use.get());
+        unsafe {
+            let vec = self.vec.get();
+            (*vec).try_reserve(additional)
+        }
+    }
+
+    /// Shrinks the capacity of the vector as much as possible, but not below
+    /// `min_capacity`. The elements' targets do not move.
+    pub fn shrink_to(&self, min_capacity: usize) {
+        assert!(!self.in_use.get());
+        unsafe {
+            let vec = self.vec.get();
+            (*vec).shrink_to(min_capacity)
+        }
+    }
+
+    /// Appends clones of all the elements of `other` to the back of the vector.
+    ///
+    /// `other` may be the same vector as `self`.
+    pub fn append_from(&self, other: &FrozenVec<T>)
+    where
+        T: Clone,
+    {
+        // clone first, as `other` may alias `self`
+        let cloned = other.with_in_use(|vec| vec.clone());
+        assert!(!self.in_use.get());
+        unsafe {
+            let vec = self.vec.get();
+            (*vec).extend(cloned)
+        }
+    }
+
+    /// Creates a new vector holding clones of the elements of all `parts`, in
+    /// order, allocating the total length up front.
+    pub fn concat(parts: &[&FrozenVec<T>]) -> FrozenVec<T>
+    where
+        T: Clone,
+    {
+        let mut vec = Vec::with_capacity(parts.iter().map(|part| part.len()).sum());
+        for part in parts {
+            part.with_in_use(|part| vec.extend_from_slice(part));
+        }
+        vec.into()
+    }
 }
 
 impl<T: StableDeref> FrozenVec<T> {
     /// Push, immediately getting a reference to the element
     pub fn push_get(&self, val: T) -> &T::Target {
+        assert!(!self.in_use.get());
         unsafe {
-            let vec = self.vec.get();
-            (*vec).push(val);
-            &*(&**(*vec).get_unchecked((*vec).len() - 1) as *const T::Target)
+            let vec = &mut *self.vec.get();
+            vec.push(val);
+            &*(&**vec.get_unchecked(vec.len() - 1) as *const T::Target)
+        }
+    }
+
+    /// Pushes `val` and returns a reference to it, unless the vector already
+    /// has as many elements as the limit given to [`FrozenVec::with_max_len`],
+    /// in which case `val` is handed back.
+    ///
+    /// Vectors created otherwise have no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    ///
+    /// let vec = FrozenVec::with_max_len(2);
+    /// assert_eq!(vec.try_push(Box::new(1)), Ok(&1));
+    /// assert_eq!(vec.try_push(Box::new(2)), Ok(&2));
+    /// assert_eq!(vec.try_push(Box::new(3)), Err(Box::new(3)));
+    /// assert_eq!(vec.len(), 2);
+    /// ```
+    pub fn try_push(&self, val: T) -> Result<&T::Target, T> {
+        assert!(!self.in_use.get());
+        unsafe {
+            let vec = &mut *self.vec.get();
+            let max_len = self.config.as_ref().and_then(|config| config.max_len);
+            if max_len.is_some_and(|max_len| vec.len() >= max_len) {
+                return Err(val);
+            }
+            vec.push(val);
+            Ok(&*(&**vec.get_unchecked(vec.len() - 1) as *const T::Target))
+        }
+    }
+
+    /// Returns the index of and a reference to the first element equal to
+    /// `value`, pushing `value` if there is none.
+    ///
+    /// This scans the vector linearly, so it is only intended for small
+    /// vectors, where it can beat hashing.
+    pub fn push_if_absent(&self, value: T) -> (usize, &T::Target)
+    where
+        T::Target: PartialEq,
+    {
+        let len = self.len();
+        for index in 0..len {
+            // safety: the vector never shrinks through `&self`
+            let existing = unsafe { self.get_unchecked(index) };
+            if *existing == *value {
+                return (index, existing);
+            }
         }
+        let index = self.len();
+        (index, self.push_get(value))
+    }
+
+    /// Like [`FrozenVec::push_if_absent`], but looks `value` up in a hash index
+    /// instead of scanning the vector, for vectors created with
+    /// [`FrozenVec::with_dedup`].
+    ///
+    /// Elements pushed by other means are indexed the next time this is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector was not created with [`FrozenVec::with_dedup`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    ///
+    /// let vec = FrozenVec::with_dedup();
+    /// assert_eq!(vec.push_dedup(Box::new("a")), (0, &"a"));
+    /// vec.push(Box::new("b"));
+    /// assert_eq!(vec.push_dedup(Box::new("b")), (1, &"b"));
+    /// assert_eq!(vec.push_dedup(Box::new("c")), (2, &"c"));
+    /// assert_eq!(vec.len(), 3);
+    /// ```
+    pub fn push_dedup(&self, value: T) -> (usize, &T::Target)
+    where
+        T::Target: Hash + Eq,
+    {
+        let dedup = self
+            .config
+            .as_ref()
+            .and_then(|config| config.dedup.as_ref())
+            .expect("FrozenVec::push_dedup requires a vector created with with_dedup");
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        // `Hash` and `Eq` implementations may read the vector through methods
+        // which don't check `in_use`, like `get`, so the vector and the index
+        // are only borrowed mutably when no user code runs
+        let ret = unsafe {
+            let vec = self.vec.get();
+            let dedup = dedup.get();
+            while (*dedup).indexed < (*vec).len() {
+                let hash = (*dedup).hash(&*(&*vec)[(*dedup).indexed]);
+                (*dedup).insert(hash);
+            }
+            let hash = (*dedup).hash(&*value);
+            let existing = (*dedup).buckets.get(&hash).and_then(|bucket| {
+                bucket
+                    .iter()
+                    .copied()
+                    .find(|&index| *(&*vec)[index] == *value)
+            });
+            match existing {
+                Some(index) => (index, &*(&*(&*vec)[index] as *const T::Target)),
+                None => {
+                    (*vec).push(value);
+                    let index = (*dedup).insert(hash);
+                    (index, &*(&*(&*vec)[index] as *const T::Target))
+                }
+            }
+        };
+        self.in_use.set(false);
+        ret
     }
 
     /// Returns a reference to an element.
     pub fn get(&self, index: usize) -> Option<&T::Target> {
         unsafe {
             let vec = self.vec.get();
-            (*vec).get(index).map(|x| &**x)
+            (&*vec).get(index).map(|x| &**x)
         }
     }
 
@@ -60,10 +300,12 @@ impl<T: StableDeref> FrozenVec<T> {
     ///
     /// ## Safety
     ///
-    /// `index` must be in bounds, i.e. it must be less than `self.len()`
+    /// `index` must be in bounds, i.e. it must be less than `self.len()`.
+    /// This is checked with a debug assertion in debug builds.
     pub unsafe fn get_unchecked(&self, index: usize) -> &T::Target {
         let vec = self.vec.get();
-        &**(*vec).get_unchecked(index)
+        debug_assert!(index < (*vec).len());
+        (&*vec).get_unchecked(index)
     }
 }
 
@@ -72,7 +314,7 @@ impl<T: Copy> FrozenVec<T> {
     pub fn get_copy(&self, index: usize) -> Option<T> {
         unsafe {
             let vec = self.vec.get();
-            (*vec).get(index).copied()
+            (&*vec).get(index).copied()
         }
     }
 }
@@ -90,6 +332,356 @@ impl<T> FrozenVec<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        unsafe {
+            let vec = self.vec.get();
+            (*vec).capacity()
+        }
+    }
+
+    /// Freezes a standard [`Vec`], without copying. This is the same as the
+    /// [`From`] conversion.
+    pub fn from_std(vec: Vec<T>) -> Self {
+        Self::from(vec)
+    }
+
+    /// Unfreezes into the standard [`Vec`], without copying.
+    pub fn into_std(self) -> Vec<T> {
+        self.vec.into_inner()
+    }
+
+    /// Freezes a plain vector. This is the same as [`FrozenVec::from_std`].
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Self::from_std(vec)
+    }
+
+    /// Converts the frozen vector into a plain vector. This is the same as
+    /// [`FrozenVec::into_std`].
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_std()
+    }
+
+    /// Converts the frozen vector into a boxed slice, dropping any excess
+    /// capacity.
+    pub fn into_boxed_slice(self) -> Box<[T]> {
+        self.vec.into_inner().into_boxed_slice()
+    }
+
+    /// Consumes the vector, returning a new one with only the elements for
+    /// which `f` returns `true`, in order.
+    pub fn into_filtered<F: FnMut(&T) -> bool>(self, mut f: F) -> FrozenVec<T> {
+        let mut vec = self.vec.into_inner();
+        vec.retain(|x| f(x));
+        vec.into()
+    }
+
+    /// Calls `f` with the slice of the elements in `range`, returning its result.
+    ///
+    /// The vector cannot be pushed to while `f` runs; doing so will panic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing.
+    pub fn for_range<R, F, U>(&self, range: R, f: F) -> U
+    where
+        R: RangeBounds<usize>,
+        F: FnOnce(&[T]) -> U,
+    {
+        let bounds: (Bound<usize>, Bound<usize>) =
+            (range.start_bound().cloned(), range.end_bound().cloned());
+        assert!(!self.in_use.get());
+        let vec = unsafe { &*self.vec.get() };
+        let slice = &vec[bounds];
+        self.in_use.set(true);
+        let ret = f(slice);
+        self.in_use.set(false);
+        ret
+    }
+
+    /// Calls `f` with a pointer to the vector's buffer and its length,
+    /// returning its result, for example to hand the elements over to FFI
+    /// without copying.
+    ///
+    /// The pointer is only valid within `f`: the vector cannot be pushed to
+    /// while `f` runs (doing so will panic), but may reallocate afterwards.
+    pub fn with_raw_parts<F, U>(&self, f: F) -> U
+    where
+        F: FnOnce(*const T, usize) -> U,
+    {
+        self.with_in_use(|vec| f(vec.as_ptr(), vec.len()))
+    }
+
+    /// Calls `f` with each consecutive chunk of `chunk_size` elements and the
+    /// index of its first element, collecting the results. The last chunk may
+    /// be shorter.
+    ///
+    /// The vector cannot be pushed to while `f` runs; doing so will panic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn with_chunks_indexed<F, U>(&self, chunk_size: usize, f: F) -> Vec<U>
+    where
+        F: Fn(usize, &[T]) -> U,
+    {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        self.with_in_use(|vec| {
+            vec.chunks(chunk_size)
+                .enumerate()
+                .map(|(i, chunk)| f(i * chunk_size, chunk))
+                .collect()
+        })
+    }
+
+    /// Calls `f` with each overlapping window of `size` consecutive elements,
+    /// in order, analogous to [slice::windows]. If the vector is shorter than
+    /// `size`, `f` is never called.
+    ///
+    /// The vector cannot be pushed to while `f` runs; doing so will panic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn for_each_window<F: FnMut(&[T])>(&self, size: usize, f: F) {
+        assert!(size != 0, "window size must be non-zero");
+        self.with_in_use(|vec| vec.windows(size).for_each(f))
+    }
+
+    /// Returns a guard which derefs to the slice of all the elements, for
+    /// when the closure of [`FrozenVec::for_range`] is inconvenient.
+    ///
+    /// The vector cannot be pushed to while the guard is alive; doing so will
+    /// panic, as will freezing it again or calling other methods which hand
+    /// out slices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    ///
+    /// let vec: FrozenVec<f64> = vec![1.0, 2.0].into();
+    /// let slice = vec.freeze_slice();
+    /// assert_eq!(slice.iter().sum::<f64>(), 3.0);
+    /// drop(slice);
+    /// vec.push(3.0);
+    /// ```
+    pub fn freeze_slice(&self) -> FrozenSlice<'_, T> {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        FrozenSlice {
+            slice: unsafe { &*self.vec.get() },
+            in_use: &self.in_use,
+        }
+    }
+
+    fn with_in_use<U>(&self, f: impl FnOnce(&Vec<T>) -> U) -> U {
+        assert!(!self.in_use.get());
+        self.in_use.set(true);
+        let ret = unsafe { f(&*self.vec.get()) };
+        self.in_use.set(false);
+        ret
+    }
+}
+
+impl<T> FrozenVec<T> {
+    /// Returns a mutable reference to the first element of the vector, or `None` if empty.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.vec_mut().first_mut()
+    }
+
+    /// Returns a mutable reference to the last element of the vector, or `None` if empty.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.vec_mut().last_mut()
+    }
+
+    /// Moves all the elements of `other` to the back of the vector.
+    pub fn append(&mut self, other: FrozenVec<T>) {
+        self.vec_mut().extend(other.vec.into_inner())
+    }
+
+    /// Splits the vector in two at the given index, returning the elements
+    /// from `at` onwards as a new `FrozenVec`.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> FrozenVec<T> {
+        self.vec_mut().split_off(at).into()
+    }
+
+    /// Removes an element from the vector and returns it, replacing it with
+    /// the last element.
+    ///
+    /// **Note** that this changes the index of the last element, which moves
+    /// to `index`. Any indices kept around for it must be fixed up by the caller.
+    ///
+    /// This is O(1), and safe, as it requires a `&mut self`, ensuring nothing is
+    /// using the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.vec_mut().swap_remove(index)
+    }
+
+    /// Inserts an element at position `index`, shifting all elements after it
+    /// to the right.
+    ///
+    /// **Note** that this changes the index of every element at or after `index`,
+    /// which all move up by one. Any indices kept around for them must be fixed up
+    /// by the caller.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, val: T) {
+        self.vec_mut().insert(index, val)
+    }
+
+    /// Sorts the vector with a comparator function, analogous to [slice::sort_by],
+    /// returning the permutation that was applied: the element now at index `i`
+    /// was previously at index `permutation[i]`.
+    ///
+    /// The sort is stable. **Note** that it changes the indices of the elements;
+    /// the returned permutation can be used to fix up any indices kept around.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn sort_by<F>(&mut self, mut f: F) -> Vec<usize>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let vec = self.vec_mut();
+        let mut permutation: Vec<usize> = (0..vec.len()).collect();
+        permutation.sort_by(|&a, &b| f(&vec[a], &vec[b]));
+
+        let mut old: Vec<Option<T>> = std::mem::take(vec).into_iter().map(Some).collect();
+        vec.extend(permutation.iter().map(|&i| old[i].take().unwrap()));
+        permutation
+    }
+
+    /// Calls `f` on every element of the vector in order, allowing it to be
+    /// modified in place. Indices and the allocation are preserved.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn map_in_place<F: FnMut(&mut T)>(&mut self, f: F) {
+        self.vec_mut().iter_mut().for_each(f)
+    }
+
+    /// Resizes the vector to `new_len`, filling new slots with values returned
+    /// by `f`, or truncating if `new_len` is smaller than the current length.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn resize_with<F: FnMut() -> T>(&mut self, new_len: usize, f: F) {
+        self.vec_mut().resize_with(new_len, f)
+    }
+
+    /// Rotates the vector in place such that the element at `mid` becomes the
+    /// first one, analogous to [slice::rotate_left].
+    ///
+    /// **Note** that this changes the index of every element. Any indices kept
+    /// around must be fixed up by the caller.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    pub fn rotate_left(&mut self, mid: usize) {
+        self.vec_mut().rotate_left(mid)
+    }
+
+    /// Rotates the vector in place such that the last `k` elements come first,
+    /// analogous to [slice::rotate_right].
+    ///
+    /// **Note** that this changes the index of every element. Any indices kept
+    /// around must be fixed up by the caller.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k > len`.
+    pub fn rotate_right(&mut self, k: usize) {
+        self.vec_mut().rotate_right(k)
+    }
+
+    /// Reverses the order of the elements in place, analogous to [slice::reverse].
+    ///
+    /// Returns the mapping from an element's old index to its new one, which
+    /// is `len - 1 - i` for the length at the time of the call. Indices kept
+    /// around can be translated with it; it is its own inverse.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// The returned mapping panics if given an index that was out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    ///
+    /// let mut vec: FrozenVec<Box<i32>> = vec![Box::new(1), Box::new(2), Box::new(3)].into();
+    /// let remap = vec.reverse();
+    /// assert_eq!(vec.get(remap(0)), Some(&1));
+    /// assert_eq!(remap(2), 0);
+    /// ```
+    pub fn reverse(&mut self) -> impl Fn(usize) -> usize {
+        let vec = self.vec_mut();
+        vec.reverse();
+        let len = vec.len();
+        move |i| {
+            assert!(i < len, "index {} out of bounds for length {}", i, len);
+            len - 1 - i
+        }
+    }
+
+    /// Removes the elements in `range` from the vector, returning them in an
+    /// iterator, analogous to [Vec::drain]. Elements which the iterator has not
+    /// yielded when dropped are dropped too.
+    ///
+    /// **Note** that every element after the range moves down by its length.
+    /// Any indices kept around for them must be fixed up by the caller.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> std::vec::Drain<'_, T> {
+        self.vec_mut().drain(range)
+    }
+
+    /// Returns an iterator that allows modifying each element of the vector.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.vec_mut().iter_mut()
+    }
 }
 
 impl<T: StableDeref> FrozenVec<T> {
@@ -109,15 +701,139 @@ impl<T: StableDeref> FrozenVec<T> {
         }
     }
     /// Returns an iterator over the vector.
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
         self.into_iter()
     }
+
+    /// Returns an iterator over the elements from index `start` on, paired
+    /// with their index in the vector.
+    ///
+    /// Like [`FrozenVec::iter`], it is safe to push to the vector during
+    /// iteration, and pushed elements are visited too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    ///
+    /// let vec: FrozenVec<Box<&str>> = vec![Box::new("a"), Box::new("b")].into();
+    /// let mut iter = vec.iter_enumerate_from(1);
+    /// assert_eq!(iter.next(), Some((1, &"b")));
+    /// vec.push(Box::new("c"));
+    /// assert_eq!(iter.next(), Some((2, &"c")));
+    /// assert_eq!(iter.next(), None);
+    /// vec.push(Box::new("d"));
+    /// assert_eq!(iter.next(), Some((3, &"d")));
+    /// assert_eq!(vec.iter_enumerate_from(5).next(), None);
+    /// ```
+    pub fn iter_enumerate_from(&self, start: usize) -> impl Iterator<Item = (usize, &T::Target)> {
+        let mut idx = start;
+        std::iter::from_fn(move || {
+            let item = self.get(idx)?;
+            idx += 1;
+            Some((idx - 1, item))
+        })
+    }
+
+    /// Returns an iterator over the last `n` elements of the vector, newest first.
+    ///
+    /// Elements pushed while iterating are not yielded.
+    pub fn iter_last(&self, n: usize) -> impl Iterator<Item = &T::Target> {
+        let len = self.len();
+        // safety: the vector never shrinks through `&self`, so every index
+        // below the length observed here stays in bounds
+        (len.saturating_sub(n)..len)
+            .rev()
+            .map(move |i| unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the number of elements for which `pred` returns `true`.
+    ///
+    /// Elements pushed while counting are not visited.
+    pub fn count<F: FnMut(&T::Target) -> bool>(&self, mut pred: F) -> usize {
+        self.fold(0, |count, x| if pred(x) { count + 1 } else { count })
+    }
+
+    /// Returns `true` if `pred` returns `true` for every element, stopping at
+    /// the first for which it does not.
+    ///
+    /// Elements pushed while testing are not visited.
+    pub fn all<F: FnMut(&T::Target) -> bool>(&self, mut pred: F) -> bool {
+        !self.any(|x| !pred(x))
+    }
+
+    /// Returns `true` if `pred` returns `true` for any element, stopping at
+    /// the first for which it does.
+    ///
+    /// Elements pushed while testing are not visited.
+    pub fn any<F: FnMut(&T::Target) -> bool>(&self, pred: F) -> bool {
+        self.iter_snapshot().any(pred)
+    }
+
+    /// Folds every element into an accumulator, in order, analogous to
+    /// [Iterator::fold].
+    ///
+    /// Elements pushed while folding are not visited.
+    pub fn fold<B, F: FnMut(B, &T::Target) -> B>(&self, init: B, f: F) -> B {
+        self.iter_snapshot().fold(init, f)
+    }
+
+    /// Builds a map from the key `key_fn` derives for each element to the
+    /// element's index. If several elements have the same key, the first one
+    /// is kept.
+    ///
+    /// `key_fn` may read the vector, e.g. with `get` or `len`, but pushing to
+    /// it or calling another method that keeps it in use, such as `for_range`,
+    /// panics. The returned map is independent of the vector, so it is not
+    /// updated by later pushes.
+    pub fn index_by<K2: Hash + Eq, F: FnMut(&T::Target) -> K2>(
+        &self,
+        mut key_fn: F,
+    ) -> HashMap<K2, usize> {
+        self.with_in_use(|vec| {
+            let mut index = HashMap::with_capacity(vec.len());
+            for (i, x) in vec.iter().enumerate() {
+                index.entry(key_fn(x)).or_insert(i);
+            }
+            index
+        })
+    }
+
+    /// Iterates over the elements present when called, ignoring later pushes.
+    fn iter_snapshot(&self) -> impl Iterator<Item = &T::Target> {
+        let len = self.len();
+        // safety: the vector never shrinks through `&self`, so every index
+        // below the length observed here stays in bounds
+        (0..len).map(move |i| unsafe { self.get_unchecked(i) })
+    }
 }
 
-impl<T: StableDeref> FrozenVec<T> {
-    /// Converts the frozen vector into a plain vector.
-    pub fn into_vec(self) -> Vec<T> {
-        self.vec.into_inner()
+#[cfg(feature = "rayon")]
+impl<T: StableDeref> FrozenVec<T>
+where
+    T::Target: Sync,
+{
+    /// Returns a parallel iterator over the elements of the vector, in order.
+    ///
+    /// The vector itself cannot be shared across threads, so references to the
+    /// elements present when called are collected up front; since the elements'
+    /// targets never move, they stay valid even if the vector is pushed to
+    /// afterwards, and such pushes are not observed by the iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elsa::FrozenVec;
+    /// use rayon::prelude::*;
+    ///
+    /// let vec: FrozenVec<Box<i32>> = (1..=4).map(Box::new).collect();
+    /// assert_eq!(vec.par_iter().sum::<i32>(), 10);
+    /// ```
+    pub fn par_iter(&self) -> rayon::vec::IntoIter<&T::Target> {
+        use rayon::iter::IntoParallelIterator;
+
+        let elements: Vec<&T::Target> = self.iter_snapshot().collect();
+        elements.into_par_iter()
     }
 }
 
@@ -198,6 +914,43 @@ impl<T: StableDeref> FrozenVec<T> {
         left
     }
 
+    /// Returns the range of indices of the elements equal to `x` in this sorted
+    /// vector, which is empty at the position `x` would be inserted at if there
+    /// are none.
+    ///
+    /// The vector must already be sorted; otherwise the range is unspecified,
+    /// as with [`FrozenVec::binary_search`].
+    pub fn equal_range(&self, x: &T::Target) -> Range<usize>
+    where
+        T::Target: Ord,
+    {
+        let start = self.partition_point(|p| p < x);
+        let end = self.partition_point(|p| p <= x);
+        start..end
+    }
+
+    /// Inserts an element into this sorted vector, keeping it sorted, and
+    /// returns the index it was inserted at. Equal elements keep their
+    /// insertion order, the new one going after them.
+    ///
+    /// The vector must already be sorted; otherwise the position is
+    /// unspecified, as with [`FrozenVec::binary_search`].
+    ///
+    /// **Note** that this changes the index of every element after the
+    /// inserted one, which all move up by one.
+    ///
+    /// This is safe, as it requires a `&mut self`, ensuring nothing is using
+    /// the 'frozen' contents.
+    pub fn binary_insert(&mut self, value: T) -> usize
+    where
+        T::Target: Ord,
+    {
+        let vec = self.vec_mut();
+        let index = vec.partition_point(|x| **x <= *value);
+        vec.insert(index, value);
+        index
+    }
+
     // TODO add more
 }
 
@@ -207,7 +960,7 @@ impl<T> std::convert::AsMut<Vec<T>> for FrozenVec<T> {
     /// This is safe, as it requires a `&mut self`, ensuring nothing is using
     /// the 'frozen' contents.
     fn as_mut(&mut self) -> &mut Vec<T> {
-        unsafe { &mut *self.vec.get() }
+        self.vec_mut()
     }
 }
 
@@ -219,8 +972,9 @@ impl<T> Default for FrozenVec<T> {
 
 impl<T: Clone> Clone for FrozenVec<T> {
     fn clone(&self) -> Self {
-        Self {
-            vec: unsafe { self.vec.get().as_ref().unwrap() }.clone().into(),
+        FrozenVec {
+            config: self.config.clone(),
+            ..self.with_in_use(|vec| vec.clone()).into()
         }
     }
 }
@@ -229,10 +983,60 @@ impl<T> From<Vec<T>> for FrozenVec<T> {
     fn from(vec: Vec<T>) -> Self {
         Self {
             vec: UnsafeCell::new(vec),
+            in_use: Cell::new(false),
+            config: None,
         }
     }
 }
 
+/// Opt-in features of a vector, enabled by its constructor
+#[derive(Debug, Default)]
+struct Config {
+    /// Set by `with_dedup`
+    dedup: Option<UnsafeCell<Dedup>>,
+    /// Set by `with_max_len`, checked by `try_push`
+    max_len: Option<usize>,
+}
+
+impl Clone for Config {
+    fn clone(&self) -> Self {
+        Config {
+            // the clone's index is rebuilt on first use
+            dedup: self.dedup.as_ref().map(|_| Default::default()),
+            max_len: self.max_len,
+        }
+    }
+}
+
+/// Hash index of the elements of a vector created with `with_dedup`
+#[derive(Debug, Default)]
+struct Dedup {
+    hasher: RandomState,
+    /// Number of elements, from the start, which are in `buckets`
+    indexed: usize,
+    /// Indices of the elements, by hash
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+impl Dedup {
+    fn hash<Q: Hash + ?Sized>(&self, value: &Q) -> u64 {
+        self.hasher.hash_one(value)
+    }
+
+    /// Records the next element, returning its index.
+    fn insert(&mut self, hash: u64) -> usize {
+        let index = self.indexed;
+        self.buckets.entry(hash).or_default().push(index);
+        self.indexed += 1;
+        index
+    }
+
+    fn clear(&mut self) {
+        self.indexed = 0;
+        self.buckets.clear();
+    }
+}
+
 impl<T: StableDeref> Index<usize> for FrozenVec<T> {
     type Output = T::Target;
     fn index(&self, idx: usize) -> &T::Target {
@@ -256,6 +1060,27 @@ impl<A> FromIterator<A> for FrozenVec<A> {
     }
 }
 
+/// Slice of all the elements of a FrozenVec, obtained via `.freeze_slice()`
+///
+/// The vector is marked as in use for as long as the guard is alive
+pub struct FrozenSlice<'a, T> {
+    slice: &'a [T],
+    in_use: &'a Cell<bool>,
+}
+
+impl<'a, T> Deref for FrozenSlice<'a, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.slice
+    }
+}
+
+impl<'a, T> Drop for FrozenSlice<'a, T> {
+    fn drop(&mut self) {
+        self.in_use.set(false);
+    }
+}
+
 /// Iterator over FrozenVec, obtained via `.iter()`
 ///
 /// It is safe to push to the vector during iteration
@@ -284,12 +1109,52 @@ impl<'a, T: StableDeref> IntoIterator for &'a FrozenVec<T> {
     }
 }
 
-impl<T: StableDeref + PartialEq> PartialEq for FrozenVec<T>
+/// Like the other comparisons and `Hash`, this looks at the dereferenced
+/// elements present when called, ignoring elements pushed meanwhile.
+impl<T: StableDeref> PartialEq for FrozenVec<T>
 where
     T::Target: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        unsafe { self.vec.get().as_ref() == other.vec.get().as_ref() }
+        self.len() == other.len() && self.iter_snapshot().eq(other.iter_snapshot())
+    }
+}
+
+impl<T: StableDeref> Eq for FrozenVec<T> where T::Target: Eq {}
+
+/// Vectors are compared lexicographically, element by element in order.
+impl<T: StableDeref> PartialOrd for FrozenVec<T>
+where
+    T::Target: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter_snapshot().partial_cmp(other.iter_snapshot())
+    }
+}
+
+/// Vectors are compared lexicographically, element by element in order.
+impl<T: StableDeref> Ord for FrozenVec<T>
+where
+    T::Target: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter_snapshot().cmp(other.iter_snapshot())
+    }
+}
+
+/// Vectors are hashed element by element in order, so the same elements in a
+/// different order will (almost always) hash differently.
+impl<T: StableDeref> Hash for FrozenVec<T>
+where
+    T::Target: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // the length goes first, as for slices, so that nested vectors with
+        // the same flattened elements hash differently
+        self.len().hash(state);
+        for x in self.iter_snapshot() {
+            x.hash(state);
+        }
     }
 }
 
@@ -363,3 +1228,450 @@ fn test_binary_search() {
     assert_eq!(vec.partition_point(|_| false), 0);
     assert_eq!(vec.partition_point(|_| true), 3);
 }
+
+#[test]
+fn test_equal_range() {
+    let vec: FrozenVec<Box<i32>> = vec![1, 2, 2, 2, 4].into_iter().map(Box::new).collect();
+
+    assert_eq!(vec.equal_range(&2), 1..4);
+    assert_eq!(vec.equal_range(&1), 0..1);
+    assert_eq!(vec.equal_range(&3), 4..4);
+    assert_eq!(vec.equal_range(&0), 0..0);
+    assert_eq!(vec.equal_range(&5), 5..5);
+}
+
+#[test]
+fn test_first_last_mut() {
+    let mut vec: FrozenVec<String> = FrozenVec::new();
+
+    assert_eq!(vec.first_mut(), None);
+    assert_eq!(vec.last_mut(), None);
+
+    vec.push("a".to_string());
+    vec.push("b".to_string());
+
+    vec.first_mut().unwrap().push('1');
+    vec.last_mut().unwrap().push('2');
+
+    assert_eq!(vec.first(), Some("a1"));
+    assert_eq!(vec.last(), Some("b2"));
+}
+
+#[test]
+fn test_append() {
+    let mut vec: FrozenVec<_> = vec!["a", "b"].into();
+    let other: FrozenVec<_> = vec!["c"].into();
+
+    vec.append_from(&other);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+
+    vec.append_from(&vec);
+    assert_eq!(vec.len(), 6);
+
+    vec.append(other);
+    assert_eq!(vec.last(), Some("c"));
+    assert_eq!(vec.len(), 7);
+}
+
+#[test]
+fn test_swap_remove() {
+    let mut vec: FrozenVec<_> = vec!["a", "b", "c", "d"].into();
+
+    assert_eq!(vec.swap_remove(1), "b");
+    assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "d", "c"]);
+    assert_eq!(vec.swap_remove(2), "c");
+    assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "d"]);
+}
+
+#[test]
+fn test_insert() {
+    let mut vec: FrozenVec<_> = vec!["a", "c"].into();
+
+    vec.insert(1, "b");
+    vec.insert(3, "d");
+    assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+}
+
+#[test]
+fn test_sort_by() {
+    let mut vec: FrozenVec<_> = vec!["c", "a", "d", "b", "a"].into();
+
+    let permutation = vec.sort_by(|a, b| a.cmp(b));
+    assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "a", "b", "c", "d"]);
+    assert_eq!(permutation, [1, 4, 3, 0, 2]);
+}
+
+#[test]
+fn test_iter_last() {
+    let vec = FrozenVec::new();
+    assert_eq!(vec.iter_last(2).count(), 0);
+    vec.push(Box::new(1));
+    vec.push(Box::new(2));
+    vec.push(Box::new(3));
+    assert_eq!(vec.iter_last(2).collect::<Vec<_>>(), vec![&3, &2]);
+    assert_eq!(vec.iter_last(5).collect::<Vec<_>>(), vec![&3, &2, &1]);
+    assert_eq!(vec.iter_last(0).count(), 0);
+}
+
+#[test]
+fn test_map_in_place() {
+    let mut vec: FrozenVec<String> = vec!["a".to_string(), "b".to_string()].into();
+    vec.map_in_place(|s| s.push('!'));
+    assert_eq!(vec.get(0), Some("a!"));
+    assert_eq!(vec.get(1), Some("b!"));
+}
+
+#[test]
+fn test_split_off() {
+    let mut vec: FrozenVec<Box<i32>> = FrozenVec::new();
+    vec.push(Box::new(1));
+    vec.push(Box::new(2));
+    vec.push(Box::new(3));
+    let tail = vec.split_off(1);
+    assert_eq!(vec.len(), 1);
+    assert_eq!(tail.iter().collect::<Vec<_>>(), vec![&2, &3]);
+}
+
+#[test]
+fn test_try_reserve() {
+    let vec = FrozenVec::new();
+    let a = vec.push_get(Box::new(1));
+    assert!(vec.try_reserve(100).is_ok());
+    assert!(vec.capacity() >= 101);
+    assert!(vec.try_reserve(usize::MAX).is_err());
+    assert_eq!(a, &1);
+}
+
+#[test]
+fn test_for_range() {
+    let vec: FrozenVec<_> = vec!["a", "b", "c", "d"].into();
+    assert_eq!(vec.for_range(1..3, |s| s.to_vec()), vec!["b", "c"]);
+    assert_eq!(vec.for_range(2.., |s| s.len()), 2);
+    assert_eq!(vec.for_range(.., |s| s.len()), 4);
+}
+
+#[test]
+#[should_panic]
+fn test_for_range_reentrant_push() {
+    let vec: FrozenVec<String> = vec!["a".to_string()].into();
+    vec.for_range(.., |_| vec.push("b".to_string()));
+}
+
+#[test]
+fn test_ord() {
+    let a: FrozenVec<_> = vec![String::from("a"), String::from("b")].into();
+    let b: FrozenVec<_> = vec![String::from("a"), String::from("c")].into();
+    let c: FrozenVec<_> = vec![String::from("a")].into();
+    assert!(a < b);
+    assert!(c < a);
+    assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+    assert_eq!(b.partial_cmp(&c), Some(Ordering::Greater));
+}
+
+#[test]
+fn test_eq_hash() {
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let a: FrozenVec<Box<str>> = vec!["a".into(), "b".into()].into();
+    let b: FrozenVec<Box<str>> = vec!["a".into()].into();
+    assert_ne!(a, b);
+    b.push("b".into());
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(hash(&a), hash(&["a", "b"][..]));
+}
+
+#[test]
+fn test_with_raw_parts() {
+    let vec: FrozenVec<u32> = vec![1, 2, 3].into();
+    let sum = vec.with_raw_parts(|ptr, len| {
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+            .iter()
+            .sum::<u32>()
+    });
+    assert_eq!(sum, 6);
+}
+
+#[test]
+fn test_concat() {
+    let a: FrozenVec<_> = vec!["a", "b"].into();
+    let b: FrozenVec<_> = vec!["c"].into();
+    let vec = FrozenVec::concat(&[&a, &b, &a]);
+    assert_eq!(
+        vec.iter().collect::<Vec<_>>(),
+        vec!["a", "b", "c", "a", "b"]
+    );
+}
+
+#[test]
+fn test_into_filtered() {
+    let vec: FrozenVec<_> = vec!["a", "bb", "c", "dd"].into();
+    let vec = vec.into_filtered(|s| s.len() == 2);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec!["bb", "dd"]);
+}
+
+#[test]
+fn test_push_if_absent() {
+    let vec = FrozenVec::new();
+    assert_eq!(vec.push_if_absent(String::from("a")), (0, "a"));
+    assert_eq!(vec.push_if_absent(String::from("b")), (1, "b"));
+    assert_eq!(vec.push_if_absent(String::from("a")), (0, "a"));
+    assert_eq!(vec.len(), 2);
+}
+
+#[test]
+fn test_with_chunks_indexed() {
+    let vec: FrozenVec<u32> = vec![1, 2, 3, 4, 5].into();
+    let sums = vec.with_chunks_indexed(2, |start, chunk| (start, chunk.iter().sum::<u32>()));
+    assert_eq!(sums, vec![(0, 3), (2, 7), (4, 5)]);
+}
+
+#[test]
+#[allow(clippy::mutable_key_type)]
+fn test_hash() {
+    use std::collections::HashMap;
+
+    let memo = HashMap::from([(FrozenVec::<Box<str>>::from(vec!["a".into(), "b".into()]), 1)]);
+    let key = FrozenVec::new();
+    key.push("a".into());
+    key.push("b".into());
+    assert_eq!(memo.get(&key), Some(&1));
+    let reversed: FrozenVec<Box<str>> = vec!["b".into(), "a".into()].into();
+    assert_eq!(memo.get(&reversed), None);
+}
+
+#[test]
+fn test_resize_with() {
+    let mut vec: FrozenVec<Box<usize>> = FrozenVec::new();
+    vec.push(Box::new(0));
+    let mut next = 0;
+    vec.resize_with(3, || {
+        next += 1;
+        Box::new(next)
+    });
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&0, &1, &2]);
+    vec.resize_with(1, || unreachable!());
+    assert_eq!(vec.len(), 1);
+}
+
+#[test]
+fn test_shrink_to() {
+    let vec: FrozenVec<Box<i32>> = FrozenVec::new();
+    vec.try_reserve(64).unwrap();
+    let first = vec.push_get(Box::new(1));
+    vec.shrink_to(8);
+    assert!(vec.capacity() >= 8 && vec.capacity() < 64);
+    assert!(std::ptr::eq(first, vec.get(0).unwrap()));
+    vec.shrink_to(0);
+    assert!(vec.capacity() >= 1 && vec.capacity() < 8);
+}
+
+#[test]
+fn test_iter_mut() {
+    let mut vec: FrozenVec<String> = vec!["a".to_string(), "b".to_string()].into();
+    for (i, s) in vec.iter_mut().enumerate() {
+        s.push_str(&i.to_string());
+    }
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec!["a0", "b1"]);
+}
+
+#[test]
+fn test_binary_insert() {
+    let mut vec: FrozenVec<Box<i32>> = vec![Box::new(1), Box::new(3)].into();
+    assert_eq!(vec.binary_insert(Box::new(2)), 1);
+    assert_eq!(vec.binary_insert(Box::new(0)), 0);
+    assert_eq!(vec.binary_insert(Box::new(3)), 4);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &3]);
+}
+
+#[test]
+#[should_panic]
+fn test_freeze_slice_push() {
+    let vec: FrozenVec<f64> = vec![1.0].into();
+    let _slice = vec.freeze_slice();
+    vec.push(2.0);
+}
+
+#[test]
+fn test_into_boxed_slice() {
+    let vec = FrozenVec::new();
+    vec.try_reserve(16).unwrap();
+    vec.push(Box::new(1));
+    vec.push(Box::new(2));
+    let boxed = vec.into_boxed_slice();
+    assert_eq!(&*boxed, &[Box::new(1), Box::new(2)]);
+}
+
+#[test]
+fn test_rotate() {
+    let mut vec: FrozenVec<Box<i32>> = vec![Box::new(1), Box::new(2), Box::new(3)].into();
+    vec.rotate_left(1);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&2, &3, &1]);
+    vec.rotate_right(2);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&3, &1, &2]);
+}
+
+#[test]
+fn test_push_dedup_after_mut() {
+    let mut vec = FrozenVec::with_dedup();
+    for x in ["a", "b", "c"].iter() {
+        vec.push_dedup(Box::new(*x));
+    }
+    let _ = vec.reverse();
+    vec.swap_remove(0);
+    assert_eq!(vec.push_dedup(Box::new("a")), (0, &"a"));
+    assert_eq!(vec.push_dedup(Box::new("c")), (2, &"c"));
+    assert_eq!(vec.clone().push_dedup(Box::new("b")), (1, &"b"));
+    assert_eq!(vec.len(), 3);
+}
+
+#[test]
+fn test_push_dedup_reentrant_read() {
+    use std::rc::Rc;
+
+    // compares by value, but also reads the vector it is stored in
+    struct Peek {
+        value: i32,
+        vec: Rc<Cell<Option<*const FrozenVec<Box<Peek>>>>>,
+    }
+
+    impl PartialEq for Peek {
+        fn eq(&self, other: &Self) -> bool {
+            if let Some(vec) = self.vec.get() {
+                let vec = unsafe { &*vec };
+                assert!(!vec.is_empty());
+                assert!(vec.get(0).is_some());
+            }
+            self.value == other.value
+        }
+    }
+
+    impl Eq for Peek {}
+
+    impl Hash for Peek {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.value.hash(state)
+        }
+    }
+
+    let cell = Rc::new(Cell::new(None));
+    let vec = FrozenVec::with_dedup();
+    cell.set(Some(&vec as *const _));
+    let peek = |value| {
+        Box::new(Peek {
+            value,
+            vec: cell.clone(),
+        })
+    };
+    assert_eq!(vec.push_dedup(peek(1)).0, 0);
+    assert_eq!(vec.push_dedup(peek(2)).0, 1);
+    assert_eq!(vec.push_dedup(peek(1)).0, 0);
+    assert_eq!(vec.len(), 2);
+}
+
+#[test]
+fn test_config_size() {
+    use std::mem::size_of;
+
+    assert_eq!(
+        size_of::<FrozenVec<Box<i32>>>(),
+        size_of::<Vec<Box<i32>>>() + 2 * size_of::<usize>()
+    );
+    let vec = FrozenVec::with_max_len(1).clone();
+    assert!(vec.try_push(Box::new(1)).is_ok());
+    assert!(vec.try_push(Box::new(2)).is_err());
+}
+
+#[test]
+fn test_push_batch() {
+    let vec = FrozenVec::new();
+    vec.push(Box::new(0));
+    vec.push_batch((1..4).map(Box::new));
+    vec.push_batch(Vec::new().into_iter());
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3]);
+}
+
+#[test]
+#[should_panic]
+fn test_push_batch_reentrant() {
+    let vec = FrozenVec::new();
+    vec.push_batch((0..2).map(|x| {
+        vec.push(Box::new(x));
+        Box::new(x)
+    }));
+}
+
+#[test]
+fn test_index_by() {
+    let vec: FrozenVec<String> = ["apple", "banana", "avocado"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let index = vec.index_by(|s| s.chars().next().unwrap());
+    assert_eq!(index.len(), 2);
+    assert_eq!(index[&'a'], 0);
+    assert_eq!(index[&'b'], 1);
+}
+
+#[test]
+fn test_reverse() {
+    let mut vec: FrozenVec<Box<i32>> = (0..4).map(Box::new).collect();
+    let remap = vec.reverse();
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&3, &2, &1, &0]);
+    for i in 0..4 {
+        assert_eq!(vec.get(remap(i)), Some(&(i as i32)));
+        assert_eq!(remap(remap(i)), i);
+    }
+}
+
+#[test]
+fn test_drain() {
+    let mut vec: FrozenVec<Box<i32>> = (1..=5).map(Box::new).collect();
+    let drained = vec.drain(1..3).collect::<Vec<_>>();
+    assert_eq!(drained, vec![Box::new(2), Box::new(3)]);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&1, &4, &5]);
+}
+
+#[test]
+fn test_for_each_window() {
+    let vec: FrozenVec<f64> = vec![1.0, 2.0, 3.0, 4.0].into();
+    let mut averages = Vec::new();
+    vec.for_each_window(2, |w| averages.push(w.iter().sum::<f64>() / 2.0));
+    assert_eq!(averages, vec![1.5, 2.5, 3.5]);
+    vec.for_each_window(5, |_| unreachable!());
+}
+
+#[test]
+fn test_aggregates() {
+    let vec: FrozenVec<Box<i32>> = (1..=4).map(Box::new).collect();
+    assert_eq!(vec.count(|x| x % 2 == 0), 2);
+    assert!(vec.all(|x| *x > 0));
+    assert!(!vec.all(|x| *x > 1));
+    assert!(vec.any(|x| *x == 3));
+    assert!(!vec.any(|x| *x == 5));
+    assert_eq!(vec.fold(0, |sum, x| sum + x), 10);
+    // elements pushed while folding are not visited
+    assert_eq!(
+        vec.fold(0, |n, _| {
+            vec.push(Box::new(0));
+            n + 1
+        }),
+        4
+    );
+}
+
+#[test]
+fn test_vec_round_trip() {
+    let vec = vec![1, 2, 3];
+    let ptr = vec.as_ptr();
+    let frozen = FrozenVec::from_vec(vec);
+    assert_eq!(frozen.get_copy(1), Some(2));
+    let vec = frozen.into_vec();
+    assert_eq!(vec.as_ptr(), ptr);
+}